@@ -0,0 +1,153 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+
+// DEFINITIONS -----
+
+/// The frequency never reaches the same value twice.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct NoRepeat;
+
+impl fmt::Display for NoRepeat {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "frequency never repeats")
+  }
+}
+
+impl Error for NoRepeat {}
+
+/// First frequency reached twice while applying `changes` over and over, starting from 0.
+///
+/// Frequency `s + k * drift` is reached on pass `k`, where `s` is a partial sum of one pass and
+/// `drift` is the sum of all changes. Two partial sums can only meet on a later pass if they're
+/// congruent modulo `drift`, and the nearest one behind in the drift's direction meets first.
+///
+/// **Note**: Returns `None` if the frequency never repeats.
+pub fn first_repeat(changes: &[i32]) -> Option<i32> {
+  if changes.is_empty() {
+    return None;
+  }
+
+  // Repeats within the first pass, which always includes 0 coming back when there's no drift.
+  let mut sums = vec![0];
+  let mut seen: HashSet<i64> = sums.iter().cloned().collect();
+  for &change in changes {
+    let sum = sums[sums.len() - 1] + change as i64;
+    if !seen.insert(sum) {
+      return Some(sum as i32);
+    }
+    sums.push(sum);
+  }
+  // A pass always ends on the drift, so it's non-zero here.
+  let drift = sums.pop().unwrap();
+
+  // Partial sums grouped by residue, in the order the drift moves through them.
+  let mut groups: HashMap<i64, Vec<(i64, usize)>> = HashMap::new();
+  for (k, &sum) in sums.iter().enumerate() {
+    groups.entry(sum.rem_euclid(drift)).or_default().push((sum * drift.signum(), k));
+  }
+
+  // Earliest `(time, frequency)` repeat, counting time in changes applied.
+  let period = sums.len() as i64;
+  groups
+    .values_mut()
+    .flat_map(|group| {
+      group.sort();
+      group.windows(2).map(|pair| {
+        let ((behind, start), (ahead, _)) = (pair[0], pair[1]);
+        let passes = (ahead - behind) / drift.abs();
+        (passes * period + start as i64, ahead * drift.signum())
+      }).collect::<Vec<_>>()
+    })
+    .min()
+    .map(|(_, freq)| freq as i32)
+}
+
+#[aoc_generator(day1)]
+pub fn parse(input: &str) -> Vec<i32> {
+  aoc_common::parse::lines(input).unwrap()
+}
+
+#[aoc(day1, part1)]
+pub fn solve1(numbers: &[i32]) -> i32 {
+  numbers.iter().sum()
+}
+
+#[aoc(day1, part2)]
+pub fn solve2(numbers: &[i32]) -> i32 {
+  let mut seen: HashSet<i32> = HashSet::new();
+  let mut current = 0;
+  seen.insert(current);
+  // Infinitely cycle over stream of numbers. Maybe for loop isn't the best?
+  for num in numbers.iter().cycle() {
+    current += num;
+    if !seen.insert(current) {
+      return current;
+    }
+  }
+  unreachable!()
+}
+
+#[aoc(day1, part2, analytic)]
+pub fn solve2_analytic(numbers: &[i32]) -> Result<i32, NoRepeat> {
+  first_repeat(numbers).ok_or(NoRepeat)
+}
+
+// TESTS -----
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test1p1() {
+    assert_eq!(solve1(&parse("+1\n-2\n+3\n+1")), 3);
+    assert_eq!(solve1(&parse("-1\n-2\n-3")), -6);
+  }
+
+  #[test]
+  fn test1p2() {
+    assert_eq!(solve2(&parse("+1\n-1")), 0);
+    assert_eq!(solve2(&parse("+3\n+3\n+4\n-2\n-4")), 10);
+    assert_eq!(solve2(&parse("-6\n+3\n+8\n+5\n-6")), 5);
+    assert_eq!(solve2(&parse("+7\n+7\n-2\n-7\n-4")), 14);
+  }
+
+  #[test]
+  fn test1p2_analytic() {
+    let samples = ["+1\n-1", "+3\n+3\n+4\n-2\n-4", "-6\n+3\n+8\n+5\n-6", "+7\n+7\n-2\n-7\n-4"];
+    for input in &samples {
+      let numbers = parse(input);
+      assert_eq!(first_repeat(&numbers), Some(solve2(&numbers)));
+    }
+  }
+
+  #[test]
+  fn test1p2_zero_drift() {
+    assert_eq!(first_repeat(&[5, -5]), Some(0));
+    assert_eq!(first_repeat(&[2, 3, -4, -1]), Some(0));
+    assert_eq!(first_repeat(&[1, 1, -1, -1]), Some(1));
+  }
+
+  #[test]
+  fn test1p2_negative_drift() {
+    assert_eq!(first_repeat(&[-2, 1]), Some(-2));
+    assert_eq!(first_repeat(&[-3, 2, -1]), Some(-3));
+    assert_eq!(first_repeat(&[-2, 5, -4]), Some(-2));
+  }
+
+  #[test]
+  fn test1p2_immediate() {
+    assert_eq!(first_repeat(&[0]), Some(0));
+    assert_eq!(first_repeat(&[4, 0]), Some(4));
+  }
+
+  #[test]
+  fn test1p2_never() {
+    assert_eq!(first_repeat(&[]), None);
+    assert_eq!(first_repeat(&[1]), None);
+    assert_eq!(first_repeat(&[-3, 1]), None);
+    assert_eq!(first_repeat(&[-7, 4, -2]), None);
+    assert_eq!(solve2_analytic(&[3]).unwrap_err().to_string(), "frequency never repeats");
+  }
+}
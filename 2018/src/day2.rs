@@ -0,0 +1,183 @@
+use std::collections::{HashMap, HashSet};
+
+// Part 1
+static USIZE_LOWERCASE_A: usize = 'a' as usize;
+
+fn two_or_three(word: &str) -> (u32, u32) {
+  let mut two_of: u32 = 0;
+  let mut three_of: u32 = 0;
+
+  // Array of counts for each letter.
+  let mut letter_counts: [u32; 26] = [0; 26];
+  for ch in word.chars() {
+    letter_counts[(ch as usize) - USIZE_LOWERCASE_A] += 1;
+  }
+
+  for count in letter_counts.iter() {
+    if *count == 2 { two_of = 1 }
+    if *count == 3 { three_of = 1 }
+  }
+
+  (two_of, three_of)
+}
+
+// Part 2
+type IndexedID = HashSet<(u8, char)>;
+
+fn index_id(id: &str) -> IndexedID {
+  let mut set = IndexedID::new();
+  for (i, ch) in id.chars().enumerate() {
+    set.insert((i as u8, ch));
+  }
+  set
+}
+
+#[aoc_generator(day2)]
+pub fn parse(input: &str) -> Vec<String> {
+  input.lines().map(|line| line.trim().to_string()).filter(|line| !line.is_empty()).collect()
+}
+
+#[aoc(day2, part1)]
+pub fn solve1(box_ids: &[String]) -> u32 {
+  let (two, three) = box_ids.iter()
+    .map(|id| two_or_three(id))
+    .fold((0, 0), |a, b| (a.0 + b.0, a.1 + b.1));
+  two * three
+}
+
+/// Letters shared by the two box IDs that differ in exactly one position.
+#[aoc(day2, part2)]
+pub fn solve2(box_ids: &[String]) -> String {
+  let sets: Vec<IndexedID> = box_ids.iter().map(|id| index_id(id)).collect();
+  for set1 in sets.iter() {
+    for set2 in sets.iter() {
+      if set1.difference(set2).count() == 1 {
+        let mut same: Vec<_> = set1.intersection(set2).collect();
+
+        // Sort the vector by the letter positions, then collect into a string.
+        same.sort();
+        return same.iter().map(|&(_, ch)| ch).collect();
+      }
+    }
+  }
+  String::new()
+}
+
+/// Two IDs at most a few positions apart, by index into the input.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Match {
+  pub a: usize,
+  pub b: usize,
+  /// Number of positions where the IDs differ.
+  pub distance: usize,
+  /// Letters the IDs share, in order.
+  pub common: String,
+}
+
+/// Every pair of equally long IDs that differ in at most `k` positions, ordered by index.
+///
+/// Each ID is hashed once per set of `k` masked positions, so two IDs land in the same bucket
+/// exactly when they agree everywhere else. That takes O(n·C(L, k)·L) time for `n` IDs of length
+/// `L`, plus the size of the buckets, instead of comparing every pair.
+pub fn near_duplicates(ids: &[String], k: usize) -> Vec<Match> {
+  let chars: Vec<Vec<char>> = ids.iter().map(|id| id.chars().collect()).collect();
+  let mut buckets: HashMap<(usize, Vec<usize>, Vec<char>), Vec<usize>> = HashMap::new();
+  for (i, id) in chars.iter().enumerate() {
+    for mask in combinations(id.len(), k.min(id.len())) {
+      let mut kept = Vec::with_capacity(id.len() - mask.len());
+      let mut masked = mask.iter().peekable();
+      for (p, &ch) in id.iter().enumerate() {
+        if masked.peek() == Some(&&p) {
+          masked.next();
+        } else {
+          kept.push(ch);
+        }
+      }
+      buckets.entry((id.len(), mask, kept)).or_default().push(i);
+    }
+  }
+
+  // Pairs that differ in fewer than `k` positions share several buckets.
+  let mut pairs = HashSet::new();
+  for bucket in buckets.values() {
+    for (n, &a) in bucket.iter().enumerate() {
+      for &b in &bucket[n + 1..] {
+        pairs.insert((a, b));
+      }
+    }
+  }
+  let mut pairs: Vec<_> = pairs.into_iter().collect();
+  pairs.sort();
+  pairs
+    .into_iter()
+    .map(|(a, b)| {
+      let same = chars[a].iter().zip(&chars[b]).filter(|(x, y)| x == y);
+      let common: String = same.map(|(&x, _)| x).collect();
+      Match { a, b, distance: chars[a].len() - common.chars().count(), common }
+    })
+    .collect()
+}
+
+/// Every increasing list of `k` positions below `n`.
+fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+  let mut out = Vec::new();
+  let mut current: Vec<usize> = (0..k).collect();
+  loop {
+    out.push(current.clone());
+    // Bump the last position that can still move right, and reset the ones after it.
+    match (0..k).rev().find(|&p| current[p] < n - k + p) {
+      Some(p) => {
+        current[p] += 1;
+        for q in p + 1..k {
+          current[q] = current[q - 1] + 1;
+        }
+      },
+      None => return out,
+    }
+  }
+}
+
+/// Same as part 2, using `near_duplicates` to avoid comparing every pair.
+#[aoc(day2, part2, masked)]
+pub fn solve2_masked(box_ids: &[String]) -> String {
+  near_duplicates(box_ids, 1)
+    .into_iter()
+    .find(|m| m.distance == 1)
+    .map_or(String::new(), |m| m.common)
+}
+
+// TESTS -----
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test2p1() {
+    let ids = parse("abcdef\nbababc\nabbcde\nabcccd\naabcdd\nabcdee\nababab");
+    assert_eq!(solve1(&ids), 12);
+  }
+
+  #[test]
+  fn test2p2() {
+    let ids = parse("abcde\nfghij\nklmno\npqrst\nfguij\naxcye\nwvxyz");
+    assert_eq!(solve2(&ids), "fgij");
+    assert_eq!(solve2_masked(&ids), "fgij");
+  }
+
+  #[test]
+  fn test2_near_duplicates() {
+    let ids = parse("abcde\nfghij\nklmno\npqrst\nfguij\naxcye\nwvxyz\nfghij\nab");
+    let found = |k| -> Vec<_> {
+      near_duplicates(&ids, k).into_iter().map(|m| (m.a, m.b, m.distance)).collect()
+    };
+    assert_eq!(found(0), vec![(1, 7, 0)]);
+    assert_eq!(found(1), vec![(1, 4, 1), (1, 7, 0), (4, 7, 1)]);
+    assert_eq!(found(2), vec![(0, 5, 2), (1, 4, 1), (1, 7, 0), (4, 7, 1)]);
+    assert_eq!(near_duplicates(&ids, 2)[0].common, "ace");
+
+    // Every pair of equal length is within 5 positions.
+    let pairs = ids.iter().filter(|id| id.len() == 5).count();
+    assert_eq!(found(5).len(), pairs * (pairs - 1) / 2);
+  }
+}
@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use aoc_common::parse::{self, ParseError};
+
+// DEFINITIONS -----
+
+/// Minute-by-minute count of how often a guard was asleep during the midnight hour.
+pub type Histogram = [u32; 60];
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum EventType {
+  BeginShift(u32),
+  FallAsleep,
+  WakeUp,
+}
+
+/// One record from the log.
+///
+/// **Note**: Derived ordering compares timestamps first, so sorting puts the log in chronological
+/// order, with shifts starting before midnight ahead of the next day's naps.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Event {
+  /// `[year, month, day, hour, minute]`
+  pub time: [u32; 5],
+  pub kind: EventType,
+}
+
+impl Event {
+  pub fn minute(&self) -> usize {
+    self.time[4] as usize
+  }
+}
+
+/// Parses lines like `[1518-11-01 00:00] Guard #10 begins shift`.
+impl FromStr for Event {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let nums = parse::ints(s).map_err(|e| e.to_string())?;
+    if nums.len() < 5 || nums.iter().any(|&n| n < 0 || n > u32::MAX as i64) {
+      return Err("expected a [yyyy-mm-dd hh:mm] timestamp".to_string());
+    }
+    let mut time = [0; 5];
+    for (t, &n) in time.iter_mut().zip(&nums) {
+      *t = n as u32;
+    }
+    if time[3] >= 24 || time[4] >= 60 {
+      return Err(format!("invalid time {:02}:{:02}", time[3], time[4]));
+    }
+
+    let kind = if s.ends_with("begins shift") {
+      match nums.get(5) {
+        Some(&id) => EventType::BeginShift(id as u32),
+        None => return Err("missing guard id".to_string()),
+      }
+    } else if s.ends_with("falls asleep") {
+      EventType::FallAsleep
+    } else if s.ends_with("wakes up") {
+      EventType::WakeUp
+    } else {
+      return Err("unknown event".to_string());
+    };
+    Ok(Event { time, kind })
+  }
+}
+
+/// Sleep histogram of every guard that appears in the chronologically sorted `events`.
+///
+/// **Note**: Naps that are still going when the next shift starts, or when the log ends, are
+/// dropped, as are naps logged before any guard and naps that wake up before they started, which
+/// can only happen if they span midnight.
+pub fn histograms(events: &[Event]) -> HashMap<u32, Histogram> {
+  let mut sleep: HashMap<u32, Histogram> = HashMap::new();
+  let mut guard = None;
+  let mut asleep = None;
+  for event in events {
+    match event.kind {
+      EventType::BeginShift(id) => {
+        guard = Some(id);
+        asleep = None;
+        sleep.entry(id).or_insert([0; 60]);
+      },
+      EventType::FallAsleep => asleep = Some(event.minute()),
+      EventType::WakeUp => {
+        match (guard, asleep.take()) {
+          (Some(id), Some(start)) if start <= event.minute() => {
+            let hist = sleep.get_mut(&id).unwrap();
+            for count in &mut hist[start..event.minute()] {
+              *count += 1;
+            }
+          },
+          _ => {},
+        }
+      },
+    }
+  }
+  sleep
+}
+
+/// The minute slept the most in `hist`, with its count. Ties go to the earliest minute.
+fn sleepiest_minute(hist: &Histogram) -> (usize, u32) {
+  hist.iter().enumerate().fold((0, 0), |best, (m, &c)| if c > best.1 { (m, c) } else { best })
+}
+
+#[aoc_generator(day4)]
+pub fn parse(input: &str) -> Result<Vec<Event>, ParseError> {
+  let mut events: Vec<Event> = parse::lines(input)?;
+  events.sort();
+  Ok(events)
+}
+
+/// Strategy 1: the guard with the most minutes asleep, times their most slept minute.
+#[aoc(day4, part1)]
+pub fn solve1(events: &[Event]) -> usize {
+  histograms(events)
+    .iter()
+    .max_by_key(|(&id, hist)| (hist.iter().sum::<u32>(), id))
+    .map_or(0, |(&id, hist)| id as usize * sleepiest_minute(hist).0)
+}
+
+/// Strategy 2: the guard most frequently asleep on the same minute, times that minute.
+#[aoc(day4, part2)]
+pub fn solve2(events: &[Event]) -> usize {
+  histograms(events)
+    .iter()
+    .map(|(&id, hist)| (sleepiest_minute(hist), id))
+    .max_by_key(|&((_, count), id)| (count, id))
+    .map_or(0, |((minute, _), id)| id as usize * minute)
+}
+
+// TESTS -----
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Sample log, shuffled to check sorting.
+  const SAMPLE: &str = "\
+[1518-11-01 00:05] falls asleep
+[1518-11-01 00:25] wakes up
+[1518-11-03 00:29] wakes up
+[1518-11-01 00:00] Guard #10 begins shift
+[1518-11-01 00:30] falls asleep
+[1518-11-01 00:55] wakes up
+[1518-11-01 23:58] Guard #99 begins shift
+[1518-11-02 00:40] falls asleep
+[1518-11-02 00:50] wakes up
+[1518-11-03 00:05] Guard #10 begins shift
+[1518-11-03 00:24] falls asleep
+[1518-11-04 00:02] Guard #99 begins shift
+[1518-11-04 00:36] falls asleep
+[1518-11-04 00:46] wakes up
+[1518-11-05 00:03] Guard #99 begins shift
+[1518-11-05 00:45] falls asleep
+[1518-11-05 00:55] wakes up";
+
+  #[test]
+  fn test4_parse() {
+    let events = parse(SAMPLE).unwrap();
+    assert_eq!(events[0].kind, EventType::BeginShift(10));
+    assert_eq!(events[5].time, [1518, 11, 1, 23, 58]);
+    assert_eq!(events[6].kind, EventType::FallAsleep);
+
+    let err = parse("[1518-11-01 00:05] falls asleep\n[1518-11-01 00:25] snores").unwrap_err();
+    assert_eq!(err.item, 2);
+    assert_eq!(err.reason, "unknown event");
+    let err = parse("[1518-11-01 00:75] wakes up").unwrap_err();
+    assert_eq!(err.reason, "invalid time 00:75");
+    let err = parse("[1518-11-01 00:00] Guard #99999999999999999999 begins shift").unwrap_err();
+    assert_eq!(err.reason, "item 6 '99999999999999999999': number too large to fit in target type");
+  }
+
+  #[test]
+  fn test4_histograms() {
+    let sleep = histograms(&parse(SAMPLE).unwrap());
+    assert_eq!(sleep[&10].iter().sum::<u32>(), 50);
+    assert_eq!(sleep[&10][24], 2);
+    assert_eq!(sleep[&99][45], 3);
+
+    // Asleep across midnight.
+    let events = parse("\
+[1518-11-01 00:00] Guard #7 begins shift
+[1518-11-01 00:50] falls asleep
+[1518-11-02 00:10] wakes up").unwrap();
+    assert_eq!(histograms(&events)[&7], [0; 60]);
+  }
+
+  #[test]
+  fn test4p1() {
+    assert_eq!(solve1(&parse(SAMPLE).unwrap()), 240);
+  }
+
+  #[test]
+  fn test4p2() {
+    assert_eq!(solve2(&parse(SAMPLE).unwrap()), 4455);
+  }
+}
@@ -0,0 +1,10 @@
+extern crate aoc_runner;
+
+#[macro_use]
+extern crate aoc_runner_derive;
+
+pub mod day1;
+pub mod day2;
+pub mod day4;
+
+aoc_lib!{ year = 2018 }
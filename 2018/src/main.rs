@@ -0,0 +1,7 @@
+extern crate advent_of_code_2018;
+extern crate aoc_runner_derive;
+extern crate aoc_runner;
+
+use aoc_runner_derive::aoc_main;
+
+aoc_main! { lib = advent_of_code_2018 }
@@ -0,0 +1,20 @@
+extern crate bohuim_aoc_2019;
+
+use bohuim_aoc_2019::day2;
+use std::{env, fs};
+
+/// Finds the day 2 noun and verb producing a target output, defaulting to the puzzle's.
+fn main() {
+  let usage = "usage: day2_solve <program> [target]";
+  let path = env::args().nth(1).expect(usage);
+  let target = env::args().nth(2).map_or(19690720, |t| t.parse().expect(usage));
+  let source = fs::read_to_string(&path).expect("couldn't read program");
+  let program = day2::parse(source.trim());
+
+  match day2::solve(&program, target) {
+    Some(s) => {
+      println!("noun={} verb={} answer={} ({})", s.noun, s.verb, 100 * s.noun + s.verb, s.method);
+    },
+    None => println!("no noun and verb produce {}", target),
+  }
+}
@@ -0,0 +1,15 @@
+extern crate bohuim_aoc_2019;
+
+use bohuim_aoc_2019::day5;
+use bohuim_aoc_2019::intcode::cfg::Cfg;
+use std::{env, fs};
+
+/// Prints the control-flow graph of an Intcode program file as Graphviz DOT.
+///
+/// Render it with e.g. `intcode_cfg input/2019/day9.txt | dot -Tsvg > day9.svg`.
+fn main() {
+  let path = env::args().nth(1).expect("usage: intcode_cfg <program>");
+  let source = fs::read_to_string(&path).expect("couldn't read program");
+  let program = day5::parse(source.trim());
+  print!("{}", Cfg::new(&program).to_dot());
+}
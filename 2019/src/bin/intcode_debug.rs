@@ -0,0 +1,18 @@
+extern crate bohuim_aoc_2019;
+
+use bohuim_aoc_2019::day5;
+use bohuim_aoc_2019::intcode::Intcode;
+use bohuim_aoc_2019::intcode::debugger::Debugger;
+use std::{env, fs, io};
+
+/// Debugs an Intcode program file, reading commands from stdin.
+fn main() {
+  let path = env::args().nth(1).expect("usage: intcode_debug <program>");
+  let source = fs::read_to_string(&path).expect("couldn't read program");
+  let program = day5::parse(source.trim());
+
+  let stdin = io::stdin();
+  Debugger::new(Intcode::new(&program))
+    .repl(stdin.lock(), io::stdout())
+    .expect("couldn't write to stdout");
+}
@@ -1,9 +1,6 @@
 #[aoc_generator(day1)]
 pub fn parse(input: &str) -> Vec<i32> {
-  input
-    .lines()
-    .map(|l| l.parse::<i32>().unwrap())
-    .collect()
+  aoc_common::parse::lines(input).unwrap()
 }
 
 #[aoc(day1, part1)]
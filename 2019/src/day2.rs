@@ -1,21 +1,24 @@
+use std::fmt;
+use crate::intcode::{address, Intcode, IntcodeError, Word};
+use crate::intcode::jit::Compiled;
+
+const TARGET: Word = 19690720;
+
 #[aoc_generator(day2)]
-pub fn parse(input: &str) -> Vec<i32> {
-  input
-    .split(",")
-    .map(|s| s.parse::<i32>().unwrap())
-    .collect()
+pub fn parse(input: &str) -> Vec<Word> {
+  aoc_common::parse::separated(input, ',').unwrap()
 }
 
 #[aoc(day2, part1)]
-pub fn part1(n: &Vec<i32>) -> i32 {
-  run(n, 12, 2)  
+pub fn part1(n: &Vec<Word>) -> Word {
+  run(n, 12, 2).unwrap()
 }
 
 #[aoc(day2, part2)]
-pub fn part2(n: &Vec<i32>) -> i32 {
+pub fn part2(n: &[Word]) -> Word {
   for noun in 0..100 {
     for verb in 0..100 {
-      if run(&n, noun, verb) == 19690720 {
+      if run(n, noun, verb) == Ok(TARGET) {
         return 100 * noun + verb;
       }
     }
@@ -23,26 +26,270 @@ pub fn part2(n: &Vec<i32>) -> i32 {
   return -1
 }
 
-pub fn run(program: &Vec<i32>, noun: i32, verb: i32) -> i32 {
-  let mut n = program.clone();
-  n[1] = noun;
-  n[2] = verb;
+#[aoc(day2, part2, jit)]
+pub fn part2_jit(n: &[Word]) -> Word {
+  // Noun and verb are only operands, so one compile serves every run.
+  let compiled = match Compiled::new(n) {
+    Some(compiled) => compiled,
+    None => return part2(n),
+  };
+  let mut mem = n.to_vec();
+  for noun in 0..100 {
+    for verb in 0..100 {
+      mem[1] = noun;
+      mem[2] = verb;
+      match compiled.run(&mem, &[]) {
+        Ok(run) if run.memory[0] == TARGET => return 100 * noun + verb,
+        _ => {},
+      }
+    }
+  }
+  -1
+}
+
+#[aoc(day2, part2, symbolic)]
+pub fn part2_symbolic(n: &[Word]) -> Word {
+  solve(n, TARGET).map_or(-1, |s| 100 * s.noun + s.verb)
+}
+
+pub fn run(program: &[Word], noun: Word, verb: Word) -> Result<Word, IntcodeError> {
+  let mut vm = Intcode::new(program);
+  vm.memory_mut()[1] = noun;
+  vm.memory_mut()[2] = verb;
+  vm.run_to_halt()?;
+  Ok(vm.memory()[0])
+}
+
+/// Finds a noun and verb in `0..100` that make the program output `target`.
+///
+/// Runs the program symbolically first, and if the output is linear in the noun and verb, solves
+/// for them directly. Otherwise, or if the direct answer doesn't check out, tries every pair.
+pub fn solve(program: &[Word], target: Word) -> Option<Solution> {
+  if let Some(expr) = symbolic(program) {
+    let direct = (0..100).find_map(|noun| {
+      let rest = target.checked_sub(expr.c)?.checked_sub(expr.noun.checked_mul(noun)?)?;
+      let verb = match expr.verb {
+        0 if rest == 0 => 0,
+        0 => return None,
+        k if rest.checked_rem(k)? == 0 => rest.checked_div(k)?,
+        _ => return None,
+      };
+      Some((noun, verb)).filter(|_| (0..100).contains(&verb))
+    });
+    if let Some((noun, verb)) = direct {
+      if run(program, noun, verb) == Ok(target) {
+        return Some(Solution { noun, verb, method: Method::Linear(expr) });
+      }
+    }
+  }
+
+  for noun in 0..100 {
+    for verb in 0..100 {
+      if run(program, noun, verb) == Ok(target) {
+        return Some(Solution { noun, verb, method: Method::Search });
+      }
+    }
+  }
+  None
+}
+
+/// Runs the program with the noun and verb left unknown, returning address 0 as a linear
+/// expression of them.
+///
+/// Gives up with `None` on anything but `add`, `mul` and `halt`, if an opcode or write address
+/// depends on the noun or verb or is out of range, or if the output isn't linear or overflows.
+/// Reads through unknown addresses are fine as long as the result is overwritten before it
+/// matters, which is how day 2 programs start.
+pub fn symbolic(program: &[Word]) -> Option<Linear> {
+  let mut mem: Vec<Value> = program.iter().map(|&x| Value::Known(Linear::constant(x))).collect();
+  let noun = Linear { c: 0, noun: 1, verb: 0 };
+  let verb = Linear { c: 0, noun: 0, verb: 1 };
+  *mem.get_mut(1)? = Value::Known(noun);
+  *mem.get_mut(2)? = Value::Known(verb);
+
+  // Concrete value of the cell at `addr`, if it has one.
+  let concrete = |mem: &Vec<Value>, addr: usize| match mem.get(addr) {
+    Some(Value::Known(x)) => x.as_constant().filter(|&x| x >= 0),
+    _ => None,
+  };
+  // Cell pointed to by the parameter at `p`.
+  let arg = |mem: &Vec<Value>, p: usize| match concrete(mem, p) {
+    Some(addr) => mem.get(addr as usize).copied().unwrap_or(Value::ZERO),
+    None => Value::Unknown,
+  };
+
+  let mut ip = 0;
+  for _ in 0..program.len() {
+    let op = concrete(&mem, ip)?;
+    if op == 99 {
+      return mem[0].known();
+    }
+    if op != 1 && op != 2 {
+      return None;
+    }
+    let val = arg(&mem, ip + 1).combine(arg(&mem, ip + 2), op);
+    let dest = address(concrete(&mem, ip + 3)?)?;
+    if dest >= mem.len() {
+      mem.resize(dest + 1, Value::ZERO);
+    }
+    mem[dest] = val;
+    ip += 4;
+  }
+  None
+}
+
+// DEFINITIONS -----
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Solution {
+  pub noun: Word,
+  pub verb: Word,
+  pub method: Method,
+}
+
+/// How a solution was found.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Method {
+  /// Solved from the program's output expression.
+  Linear(Linear),
+  /// Found by trying every noun and verb.
+  Search,
+}
 
-  let mut i = 0;
-  while n[i] != 99 {
-    let op = n[i];
-    let pos1 = n[i+1] as usize;
-    let pos2 = n[i+2] as usize;
-    let posr = n[i+3] as usize;
-    
-    let x1 = n[pos1];
-    let x2 = n[pos2];
+/// `c + self.noun * noun + self.verb * verb`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Linear {
+  pub c: Word,
+  pub noun: Word,
+  pub verb: Word,
+}
+impl Linear {
+  fn constant(c: Word) -> Self {
+    Linear { c, noun: 0, verb: 0 }
+  }
+
+  fn as_constant(self) -> Option<Word> {
+    if self.noun == 0 && self.verb == 0 { Some(self.c) } else { None }
+  }
+
+  fn checked_add(self, other: Linear) -> Option<Linear> {
+    Some(Linear {
+      c: self.c.checked_add(other.c)?,
+      noun: self.noun.checked_add(other.noun)?,
+      verb: self.verb.checked_add(other.verb)?,
+    })
+  }
+
+  fn checked_scale(self, k: Word) -> Option<Linear> {
+    Some(Linear {
+      c: self.c.checked_mul(k)?,
+      noun: self.noun.checked_mul(k)?,
+      verb: self.verb.checked_mul(k)?,
+    })
+  }
+}
 
-    let r = if op == 1 { x1 + x2 } else { x1 * x2 };
-    n[posr] = r;
+impl fmt::Display for Linear {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{} + {} * noun + {} * verb", self.c, self.noun, self.verb)
+  }
+}
 
-    i += 4;
+impl fmt::Display for Method {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Method::Linear(expr) => write!(f, "solved {}", expr),
+      Method::Search => write!(f, "searched"),
+    }
   }
+}
 
-  n[0]
+// A memory cell during symbolic execution.
+#[derive(Clone, Copy)]
+enum Value {
+  Known(Linear),
+  // Depends on the noun or verb in a way that isn't linear.
+  Unknown,
+}
+impl Value {
+  const ZERO: Value = Value::Known(Linear { c: 0, noun: 0, verb: 0 });
+
+  fn known(self) -> Option<Linear> {
+    match self {
+      Value::Known(x) => Some(x),
+      Value::Unknown => None,
+    }
+  }
+
+  // Result of `add` (1) or `mul` (2) on two cells.
+  fn combine(self, other: Value, op: Word) -> Value {
+    let (x, y) = match (self, other) {
+      (Value::Known(x), Value::Known(y)) => (x, y),
+      _ => return Value::Unknown,
+    };
+    let result = match (op, x.as_constant(), y.as_constant()) {
+      (1, _, _) => x.checked_add(y),
+      (_, Some(k), _) => y.checked_scale(k),
+      (_, _, Some(k)) => x.checked_scale(k),
+      _ => None,
+    };
+    // An overflowing coefficient is as good as unknown.
+    result.map_or(Value::Unknown, Value::Known)
+  }
+}
+
+// TESTS -----
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Reads through the noun and verb as addresses first, then computes 12345 + 100 * noun + verb.
+  const LINEAR: [Word; 19] = [1,0,0,3, 2,1,17,3, 1,3,2,3, 1,3,18,0, 99, 100, 12345];
+
+  #[test]
+  fn test2_symbolic() {
+    let expr = symbolic(&LINEAR).unwrap();
+    assert_eq!(expr, Linear { c: 12345, noun: 100, verb: 1 });
+    assert_eq!(expr.to_string(), "12345 + 100 * noun + 1 * verb");
+  }
+
+  #[test]
+  fn test2_solve_linear() {
+    let solution = solve(&LINEAR, 12345 + 1202).unwrap();
+    assert_eq!((solution.noun, solution.verb), (12, 2));
+    assert_eq!(solution.method, Method::Linear(Linear { c: 12345, noun: 100, verb: 1 }));
+    assert_eq!(solve(&LINEAR, 0), None);
+  }
+
+  #[test]
+  fn test2_solve_fallback() {
+    // noun * verb isn't linear.
+    let program = vec![1,0,0,3, 2,1,2,0, 99];
+    assert_eq!(symbolic(&program), None);
+    let solution = solve(&program, 89 * 97).unwrap();
+    assert_eq!(solution, Solution { noun: 89, verb: 97, method: Method::Search });
+  }
+
+  #[test]
+  fn test2_solve_overflow() {
+    // Computes noun * 2^40 * 2^40 + verb, which only fits for noun = 0.
+    let program = vec![1,0,0,3, 2,1,17,3, 2,3,17,3, 1,3,2,0, 99, 1 << 40];
+    assert_eq!(symbolic(&program), None);
+    let solution = solve(&program, 5).unwrap();
+    assert_eq!(solution, Solution { noun: 0, verb: 5, method: Method::Search });
+
+    // Writes far past the memory limit.
+    assert_eq!(symbolic(&[1,0,0,1 << 50, 99]), None);
+  }
+
+  #[test]
+  fn test2p2_jit_fallback() {
+    // Computes TARGET - 1202 + 100 * noun + verb. The untaken `jz` points into itself, so the
+    // program can't be compiled.
+    let program = vec![1,0,0,3, 2,1,20,3, 1,3,2,3, 1,3,21,0, 1006,4,17, 99, 100, TARGET - 1202];
+    assert!(Compiled::new(&program).is_none());
+    assert_eq!(part2_jit(&program), 1202);
+    assert_eq!(part2_jit(&program), part2(&program));
+  }
 }
@@ -1,20 +1,34 @@
 extern crate superslice;
 use superslice::*;
 use crate::day5;
+use crate::intcode::{Intcode, IntcodeError, Status, Word};
 
 #[aoc_generator(day7)]
-pub fn parse(input: &str) -> Vec<i32> {
+pub fn parse(input: &str) -> Vec<Word> {
   day5::parse(input)
 }
 
 #[aoc(day7, part1)]
-pub fn part1(n: &Vec<i32>) -> i32 {
-  let mut max_out = i32::min_value();
+pub fn part1(n: &[Word]) -> Word {
+  max_signal(n, [0, 1, 2, 3, 4], amplify)
+}
+
+#[aoc(day7, part2)]
+pub fn part2(n: &[Word]) -> Word {
+  max_signal(n, [5, 6, 7, 8, 9], feedback)
+}
+
+// Tries every ordering of `phase_seq` and returns the highest signal produced by `chain`.
+fn max_signal(
+  n: &[Word],
+  mut phase_seq: [Word; 5],
+  chain: fn(&[Word], &[Word; 5]) -> Result<Word, IntcodeError>,
+) -> Word {
+  let mut max_out = Word::MIN;
 
   let permutations = 5*4*3*2*1;
-  let mut phase_seq = [0i32, 1, 2, 3, 4];
   for _ in 0..permutations {
-    let out = amplify(n.clone(), &phase_seq);
+    let out = chain(n, &phase_seq).unwrap_or_else(|e| panic!("{:?}: {}", phase_seq, e));
     if out > max_out {
       max_out = out;
     }
@@ -23,18 +37,60 @@ pub fn part1(n: &Vec<i32>) -> i32 {
   max_out
 }
 
-fn amplify(mut program: Vec<i32>, phase_seq: &[i32; 5]) -> i32 {
-  let mut amps = [0i32; 5];
-  for i in 0..5 {
-    let input = if i == 0 { 0 } else { amps[i-1] };
-    let output = day5::run_mult_args(&mut program, vec![phase_seq[i], input]);
-    amps[i] =
-      match output.len() {
-        0 => i32::min_value(),
-        l => output[l - 1],
+// Boots one machine per amplifier, each with its own memory and its phase setting queued.
+fn boot(program: &[Word], phase_seq: &[Word; 5]) -> Vec<Intcode> {
+  phase_seq
+    .iter()
+    .map(|&phase| {
+      let mut amp = Intcode::new(program);
+      amp.push_input(phase);
+      amp
+    })
+    .collect()
+}
+
+// Passes the signal through amplifiers A to E once.
+fn amplify(program: &[Word], phase_seq: &[Word; 5]) -> Result<Word, IntcodeError> {
+  let mut signal = 0;
+  for amp in boot(program, phase_seq).iter_mut() {
+    amp.push_input(signal);
+    signal = match amp.run_until_io()? {
+      Status::Output(val) => val,
+      Status::NeedsInput => return Err(IntcodeError::InputExhausted { ip: amp.ip() }),
+      _ => return Err(IntcodeError::MissingOutput { ip: amp.ip() }),
+    };
+  }
+  Ok(signal)
+}
+
+// Routes E's output back into A until the amplifiers halt, returning E's last output.
+//
+// Only A may halt, and only after E has produced something: any other amplifier halting would
+// leave the loop without the output it was waiting for.
+fn feedback(program: &[Word], phase_seq: &[Word; 5]) -> Result<Word, IntcodeError> {
+  let mut amps = boot(program, phase_seq);
+  let mut signal = 0;
+  let mut last = None;
+  loop {
+    for (k, amp) in amps.iter_mut().enumerate() {
+      amp.push_input(signal);
+      match amp.run_until_io()? {
+        Status::Output(val) => {
+          signal = val;
+          if k == phase_seq.len() - 1 {
+            last = Some(val);
+          }
+        },
+        Status::Halted => {
+          return match (k, last) {
+            (0, Some(out)) => Ok(out),
+            _ => Err(IntcodeError::MissingOutput { ip: amp.ip() }),
+          };
+        },
+        _ => return Err(IntcodeError::InputExhausted { ip: amp.ip() }),
       }
+    }
   }
-  amps[4]
 }
 
 #[cfg(test)]
@@ -60,5 +116,36 @@ mod tests {
                        1002,33,7,33,1,33,31,31,1,32,31,31,4,31,99,0,0,0];
     assert_eq!(part1(&program), 65210);
   }
-}
 
+  #[test]
+  fn test7p2_t1() {
+    let program = vec![3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,
+                       27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5];
+    assert_eq!(part2(&program), 139629729);
+  }
+
+  #[test]
+  fn test7p2_t2() {
+    let program = vec![3,52,1001,52,-5,52,3,53,1,52,56,54,1007,54,5,55,1005,55,26,1001,54,
+                       -5,54,1105,1,12,1,53,54,53,1008,54,0,55,1001,55,1,55,2,53,55,53,4,
+                       53,1001,56,-1,56,1005,56,6,99,0,0,0,0,10];
+    assert_eq!(part2(&program), 18216);
+  }
+
+  #[test]
+  fn test7_errors() {
+    // Halts straight away, and wants a third input, respectively.
+    let (silent, greedy) = (vec![99], vec![3,0,3,0,3,0,99]);
+    assert_eq!(amplify(&silent, &[0, 1, 2, 3, 4]), Err(IntcodeError::MissingOutput { ip: 0 }));
+    assert_eq!(amplify(&greedy, &[0, 1, 2, 3, 4]), Err(IntcodeError::InputExhausted { ip: 4 }));
+    assert_eq!(feedback(&greedy, &[5, 6, 7, 8, 9]), Err(IntcodeError::InputExhausted { ip: 4 }));
+
+    // Reads the phase and halts, before any amplifier has output.
+    let quiet = vec![3,0,99];
+    assert_eq!(feedback(&quiet, &[5, 6, 7, 8, 9]), Err(IntcodeError::MissingOutput { ip: 2 }));
+
+    // Echoes every signal, but only loops with phase 5, so B halts in the second round.
+    let once = vec![3,20,3,21,4,21,1008,20,5,22,1005,22,2,99,0,0,0,0,0,0,0,0,0];
+    assert_eq!(feedback(&once, &[5, 6, 7, 8, 9]), Err(IntcodeError::MissingOutput { ip: 13 }));
+  }
+}
@@ -0,0 +1,615 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+
+pub mod asm;
+pub mod cfg;
+pub mod debugger;
+pub mod device;
+pub mod disasm;
+pub mod jit;
+pub mod network;
+pub mod profile;
+pub mod snapshot;
+
+#[cfg(test)]
+mod differential;
+
+/// Machine word. Later puzzles need values well past `i32`.
+pub type Word = i64;
+
+/// Highest address a program may read, write or jump to. Memory grows up to it on demand, so a
+/// broken program can't make the machine allocate without bound.
+pub const MEMORY_LIMIT: usize = 1 << 20;
+
+/// Intcode machine that owns its memory, instruction pointer and I/O queues.
+///
+/// Inputs are fed with `push_input` and outputs are handed back as they are produced, so a caller
+/// can drive several machines against each other (see the amplifiers in day 7). Cloning copies
+/// the whole state, which is how to branch a machine and backtrack; see `snapshot` for saving it
+/// to disk.
+#[derive(Clone, Debug)]
+pub struct Intcode {
+  mem: Vec<Word>,
+  ip: usize,
+  rb: Word,
+  inputs: VecDeque<Word>,
+  halted: bool,
+  steps: usize,
+  step_limit: Option<usize>,
+}
+
+/// Result of executing one instruction, or of running until the machine needs the outside world.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Status {
+  /// Executed an instruction that neither consumed input nor produced output.
+  Running,
+  /// Blocked on an `in` instruction with an empty input queue.
+  NeedsInput,
+  /// Produced an output value.
+  Output(Word),
+  /// Reached `99`.
+  Halted,
+}
+
+/// Hook that observes every instruction a machine executes.
+pub trait Tracer {
+  /// Called after the instruction at `ip` has run. `inst` is decoded from before it ran, so
+  /// self-modifying writes to its own words don't show up here.
+  fn trace(&mut self, ip: usize, inst: &Instruction, vm: &Intcode);
+}
+
+/// Reasons a program can fail. Each carries the address of the offending instruction.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum IntcodeError {
+  UnknownOpcode { ip: usize, opcode: Word },
+  UnknownMode { ip: usize, mode: Word },
+  InputExhausted { ip: usize },
+  /// A read, write or jump targeted a negative address, or one past `MEMORY_LIMIT`.
+  InvalidAddress { ip: usize, addr: Word },
+  ImmediateWrite { ip: usize },
+  StepLimitExceeded { ip: usize, limit: usize },
+  /// Halted where the caller was waiting for an output.
+  MissingOutput { ip: usize },
+  /// An `add`, `mul` or relative base adjustment overflowed a `Word`.
+  Overflow { ip: usize },
+}
+impl fmt::Display for IntcodeError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      IntcodeError::UnknownOpcode { ip, opcode } =>
+        write!(f, "unknown opcode {} at {}", opcode, ip),
+      IntcodeError::UnknownMode { ip, mode } =>
+        write!(f, "unknown parameter mode {} at {}", mode, ip),
+      IntcodeError::InputExhausted { ip } =>
+        write!(f, "input exhausted at {}", ip),
+      IntcodeError::InvalidAddress { ip, addr } =>
+        write!(f, "invalid address {} at {}", addr, ip),
+      IntcodeError::ImmediateWrite { ip } =>
+        write!(f, "write through immediate parameter at {}", ip),
+      IntcodeError::StepLimitExceeded { ip, limit } =>
+        write!(f, "step limit of {} exceeded at {}", limit, ip),
+      IntcodeError::MissingOutput { ip } =>
+        write!(f, "halted at {} without producing output", ip),
+      IntcodeError::Overflow { ip } =>
+        write!(f, "arithmetic overflow at {}", ip),
+    }
+  }
+}
+impl Error for IntcodeError {}
+
+impl Intcode {
+  /// Creates a machine with its own copy of `program` as memory.
+  pub fn new(program: &[Word]) -> Self {
+    Intcode {
+      mem: program.to_vec(),
+      ip: 0,
+      rb: 0,
+      inputs: VecDeque::new(),
+      halted: false,
+      steps: 0,
+      step_limit: None,
+    }
+  }
+
+  /// Fails with `StepLimitExceeded` once more than `limit` instructions have been executed.
+  pub fn with_step_limit(mut self, limit: usize) -> Self {
+    self.step_limit = Some(limit);
+    self
+  }
+
+  /// Queues a value for the next `in` instruction.
+  pub fn push_input(&mut self, value: Word) {
+    self.inputs.push_back(value);
+  }
+
+  /// Memory as written so far. Addresses past the end read as 0.
+  pub fn memory(&self) -> &[Word] {
+    &self.mem
+  }
+
+  pub fn memory_mut(&mut self) -> &mut [Word] {
+    &mut self.mem
+  }
+
+  pub fn ip(&self) -> usize {
+    self.ip
+  }
+
+  /// Relative base used by mode 2 parameters.
+  pub fn relative_base(&self) -> Word {
+    self.rb
+  }
+
+  /// Number of instructions executed so far.
+  pub fn steps(&self) -> usize {
+    self.steps
+  }
+
+  pub fn is_halted(&self) -> bool {
+    self.halted
+  }
+
+  /// Executes a single instruction.
+  ///
+  /// An `in` with no queued input leaves the machine untouched and returns `NeedsInput`, so the
+  /// same instruction is retried on the next call. Errors also leave the machine at the failing
+  /// instruction.
+  pub fn step(&mut self) -> Result<Status, IntcodeError> {
+    if self.halted {
+      return Ok(Status::Halted);
+    }
+    let i = self.ip;
+    if let Some(limit) = self.step_limit {
+      if self.steps >= limit {
+        return Err(IntcodeError::StepLimitExceeded { ip: i, limit });
+      }
+    }
+
+    let inst = self.read(i);
+    let op = inst % 100;
+    let mode = parse_modes(i, inst / 100)?;
+
+    let status = match op {
+      1 => {
+        let r = self.arg(i + 1, mode[0])?.checked_add(self.arg(i + 2, mode[1])?);
+        let r = r.ok_or(IntcodeError::Overflow { ip: i })?;
+        self.write(i + 3, mode[2], r)?;
+        self.ip = i + 4;
+        Status::Running
+      },
+      2 => {
+        let r = self.arg(i + 1, mode[0])?.checked_mul(self.arg(i + 2, mode[1])?);
+        let r = r.ok_or(IntcodeError::Overflow { ip: i })?;
+        self.write(i + 3, mode[2], r)?;
+        self.ip = i + 4;
+        Status::Running
+      },
+      3 => {
+        let input = match self.inputs.front() {
+          Some(&x) => x,
+          None => return Ok(Status::NeedsInput),
+        };
+        self.write(i + 1, mode[0], input)?;
+        self.inputs.pop_front();
+        self.ip = i + 2;
+        Status::Running
+      },
+      4 => {
+        let val = self.arg(i + 1, mode[0])?;
+        self.ip = i + 2;
+        Status::Output(val)
+      },
+      // Jump if true
+      5 => {
+        let cond = self.arg(i + 1, mode[0])?;
+        let line = self.arg(i + 2, mode[1])?;
+        self.ip = if cond != 0 { self.addr(line)? } else { i + 3 };
+        Status::Running
+      },
+      // Jump if false
+      6 => {
+        let cond = self.arg(i + 1, mode[0])?;
+        let line = self.arg(i + 2, mode[1])?;
+        self.ip = if cond == 0 { self.addr(line)? } else { i + 3 };
+        Status::Running
+      },
+      // Less-than comparison
+      7 => {
+        let r = if self.arg(i + 1, mode[0])? < self.arg(i + 2, mode[1])? { 1 } else { 0 };
+        self.write(i + 3, mode[2], r)?;
+        self.ip = i + 4;
+        Status::Running
+      },
+      // Equals comparison
+      8 => {
+        let r = if self.arg(i + 1, mode[0])? == self.arg(i + 2, mode[1])? { 1 } else { 0 };
+        self.write(i + 3, mode[2], r)?;
+        self.ip = i + 4;
+        Status::Running
+      },
+      // Adjust relative base
+      9 => {
+        let rb = self.rb.checked_add(self.arg(i + 1, mode[0])?);
+        self.rb = rb.ok_or(IntcodeError::Overflow { ip: i })?;
+        self.ip = i + 2;
+        Status::Running
+      },
+      99 => {
+        self.halted = true;
+        Status::Halted
+      },
+      _ => return Err(IntcodeError::UnknownOpcode { ip: i, opcode: inst }),
+    };
+    self.steps += 1;
+    Ok(status)
+  }
+
+  /// Same as `step`, reporting the instruction to `tracer` if one was executed.
+  pub fn step_traced<T: Tracer>(&mut self, tracer: &mut T) -> Result<Status, IntcodeError> {
+    if self.halted {
+      return Ok(Status::Halted);
+    }
+    let ip = self.ip;
+    let inst = decode(&self.mem, ip)?;
+    let steps = self.steps;
+    let status = self.step()?;
+    if self.steps > steps {
+      tracer.trace(ip, &inst, self);
+    }
+    Ok(status)
+  }
+
+  /// Steps until the machine blocks on input, produces an output or halts.
+  pub fn run_until_io(&mut self) -> Result<Status, IntcodeError> {
+    loop {
+      match self.step()? {
+        Status::Running => continue,
+        status => return Ok(status),
+      }
+    }
+  }
+
+  /// Same as `run_until_io`, reporting every executed instruction to `tracer`.
+  pub fn run_until_io_traced<T: Tracer>(
+    &mut self,
+    tracer: &mut T,
+  ) -> Result<Status, IntcodeError> {
+    loop {
+      match self.step_traced(tracer)? {
+        Status::Running => continue,
+        status => return Ok(status),
+      }
+    }
+  }
+
+  /// Runs to completion with the queued inputs, collecting every output.
+  ///
+  /// Fails with `InputExhausted` if the machine asks for more input than was queued.
+  pub fn run_to_halt(&mut self) -> Result<Vec<Word>, IntcodeError> {
+    let mut outputs = Vec::new();
+    loop {
+      match self.run_until_io()? {
+        Status::Output(val) => outputs.push(val),
+        Status::NeedsInput => return Err(IntcodeError::InputExhausted { ip: self.ip }),
+        _ => return Ok(outputs),
+      }
+    }
+  }
+
+  // Retreives the parameter at `p` interpretted according to mode `m`.
+  fn arg(&self, p: usize, m: Mode) -> Result<Word, IntcodeError> {
+    let x = self.read(p);
+    Ok(match m {
+      Mode::POS => self.read(self.addr(x)?),
+      Mode::IMM => x,
+      Mode::REL => self.read(self.addr(self.relative(x)?)?),
+    })
+  }
+
+  // Writes `val` to the address held by parameter `p`, growing memory up to `MEMORY_LIMIT`.
+  fn write(&mut self, p: usize, m: Mode, val: Word) -> Result<(), IntcodeError> {
+    let x = self.read(p);
+    let pr = match m {
+      Mode::POS => self.addr(x)?,
+      Mode::IMM => return Err(IntcodeError::ImmediateWrite { ip: self.ip }),
+      Mode::REL => self.addr(self.relative(x)?)?,
+    };
+    if pr >= self.mem.len() {
+      self.mem.resize(pr + 1, 0);
+    }
+    self.mem[pr] = val;
+    Ok(())
+  }
+
+  // Checks that `addr` can index memory.
+  fn addr(&self, addr: Word) -> Result<usize, IntcodeError> {
+    address(addr).ok_or(IntcodeError::InvalidAddress { ip: self.ip, addr })
+  }
+
+  // Address of a mode 2 parameter with value `x`.
+  fn relative(&self, x: Word) -> Result<Word, IntcodeError> {
+    self.rb.checked_add(x).ok_or(IntcodeError::Overflow { ip: self.ip })
+  }
+
+  fn read(&self, addr: usize) -> Word {
+    self.mem.get(addr).copied().unwrap_or(0)
+  }
+}
+
+/// `addr` as an index into memory, if it's between 0 and `MEMORY_LIMIT`.
+pub fn address(addr: Word) -> Option<usize> {
+  if 0 <= addr && addr <= MEMORY_LIMIT as Word {
+    Some(addr as usize)
+  } else {
+    None
+  }
+}
+
+/// Decodes the instruction at `ip` without executing it.
+pub fn decode(mem: &[Word], ip: usize) -> Result<Instruction, IntcodeError> {
+  let inst = mem.get(ip).copied().unwrap_or(0);
+  let op = Opcode::from(inst % 100).ok_or(IntcodeError::UnknownOpcode { ip, opcode: inst })?;
+  let modes = parse_modes(ip, inst / 100)?;
+  let mut params = [Param { mode: Mode::POS, value: 0 }; 3];
+  for (k, param) in params.iter_mut().enumerate().take(op.arity()) {
+    *param = Param { mode: modes[k], value: mem.get(ip + 1 + k).copied().unwrap_or(0) };
+  }
+  Ok(Instruction { op, params })
+}
+
+fn parse_modes(ip: usize, modes: Word) -> Result<[Mode; 3], IntcodeError> {
+  let mut m = modes;
+  let mut parsed = [Mode::POS; 3];
+  for mode in parsed.iter_mut() {
+    *mode = Mode::from(m % 10).ok_or(IntcodeError::UnknownMode { ip, mode: m % 10 })?;
+    m /= 10;
+  }
+  Ok(parsed)
+}
+
+// DEFINITIONS --------------------
+
+/// Parameter mode, the hundreds digit and up of an instruction.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode { POS, IMM, REL }
+impl Mode {
+  fn from(x: Word) -> Option<Self> {
+    match x {
+      0 => Some(Mode::POS),
+      1 => Some(Mode::IMM),
+      2 => Some(Mode::REL),
+      _ => None,
+    }
+  }
+
+  pub fn digit(self) -> Word {
+    match self {
+      Mode::POS => 0,
+      Mode::IMM => 1,
+      Mode::REL => 2,
+    }
+  }
+}
+
+/// Operation named by the low two digits of an instruction.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Opcode { Add, Mul, In, Out, Jnz, Jz, Lt, Eq, Arb, Halt }
+impl Opcode {
+  pub const ALL: [Opcode; 10] = [
+    Opcode::Add, Opcode::Mul, Opcode::In, Opcode::Out, Opcode::Jnz,
+    Opcode::Jz, Opcode::Lt, Opcode::Eq, Opcode::Arb, Opcode::Halt,
+  ];
+
+  pub fn from(x: Word) -> Option<Self> {
+    Opcode::ALL.iter().copied().find(|op| op.code() == x)
+  }
+
+  pub fn code(self) -> Word {
+    match self {
+      Opcode::Add => 1,
+      Opcode::Mul => 2,
+      Opcode::In => 3,
+      Opcode::Out => 4,
+      Opcode::Jnz => 5,
+      Opcode::Jz => 6,
+      Opcode::Lt => 7,
+      Opcode::Eq => 8,
+      Opcode::Arb => 9,
+      Opcode::Halt => 99,
+    }
+  }
+
+  pub fn mnemonic(self) -> &'static str {
+    match self {
+      Opcode::Add => "add",
+      Opcode::Mul => "mul",
+      Opcode::In => "in",
+      Opcode::Out => "out",
+      Opcode::Jnz => "jnz",
+      Opcode::Jz => "jz",
+      Opcode::Lt => "lt",
+      Opcode::Eq => "eq",
+      Opcode::Arb => "arb",
+      Opcode::Halt => "halt",
+    }
+  }
+
+  /// Number of parameters following the opcode.
+  pub fn arity(self) -> usize {
+    match self {
+      Opcode::Add | Opcode::Mul | Opcode::Lt | Opcode::Eq => 3,
+      Opcode::Jnz | Opcode::Jz => 2,
+      Opcode::In | Opcode::Out | Opcode::Arb => 1,
+      Opcode::Halt => 0,
+    }
+  }
+
+  /// Whether the last parameter is a write target.
+  pub fn writes(self) -> bool {
+    matches!(self, Opcode::Add | Opcode::Mul | Opcode::Lt | Opcode::Eq | Opcode::In)
+  }
+}
+
+/// A raw parameter word along with how it should be interpretted.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Param {
+  pub mode: Mode,
+  pub value: Word,
+}
+
+/// A decoded instruction. Only the first `op.arity()` parameters are meaningful.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Instruction {
+  pub op: Opcode,
+  params: [Param; 3],
+}
+impl Instruction {
+  /// Builds an instruction from its parameters.
+  ///
+  /// **Note**: Panics if `params` doesn't match the opcode's arity.
+  pub fn new(op: Opcode, params: &[Param]) -> Self {
+    assert_eq!(params.len(), op.arity(), "{} takes {} parameters", op.mnemonic(), op.arity());
+    let mut inst = Instruction { op, params: [Param { mode: Mode::POS, value: 0 }; 3] };
+    inst.params[..params.len()].copy_from_slice(params);
+    inst
+  }
+
+  pub fn params(&self) -> &[Param] {
+    &self.params[..self.op.arity()]
+  }
+
+  /// Number of words the instruction takes up in memory.
+  pub fn size(&self) -> usize {
+    1 + self.op.arity()
+  }
+
+  /// Re-encodes the instruction into memory words.
+  pub fn encode(&self) -> Vec<Word> {
+    let mut inst = self.op.code();
+    let mut scale = 100;
+    for param in self.params() {
+      inst += param.mode.digit() * scale;
+      scale *= 10;
+    }
+    let mut words = vec![inst];
+    words.extend(self.params().iter().map(|p| p.value));
+    words
+  }
+}
+
+// TESTS --------------------
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::asm::fixtures::DOUBLER;
+
+  #[test]
+  fn test_step_add() {
+    let mut vm = Intcode::new(&[1,5,6,7,99,30,12,0]);
+    assert_eq!(vm.step(), Ok(Status::Running));
+    assert_eq!(vm.ip(), 4);
+    assert_eq!(vm.memory()[7], 42);
+    assert_eq!(vm.step(), Ok(Status::Halted));
+    assert!(vm.is_halted());
+  }
+
+  #[test]
+  fn test_input_one_at_a_time() {
+    let mut vm = Intcode::new(&DOUBLER);
+    assert_eq!(vm.run_until_io(), Ok(Status::NeedsInput));
+    vm.push_input(4);
+    assert_eq!(vm.run_until_io(), Ok(Status::Output(8)));
+    assert_eq!(vm.run_until_io(), Ok(Status::NeedsInput));
+    vm.push_input(-3);
+    assert_eq!(vm.run_until_io(), Ok(Status::Output(-6)));
+    vm.push_input(0);
+    assert_eq!(vm.run_until_io(), Ok(Status::Halted));
+  }
+
+  #[test]
+  fn test9_quine() {
+    let program = vec![109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99];
+    assert_eq!(Intcode::new(&program).run_to_halt(), Ok(program));
+  }
+
+  #[test]
+  fn test9_large_mul() {
+    let program = vec![1102,34915192,34915192,7,4,7,99,0];
+    assert_eq!(Intcode::new(&program).run_to_halt(), Ok(vec![1219070632396864]));
+  }
+
+  #[test]
+  fn test9_large_imm() {
+    let program = vec![104,1125899906842624,99];
+    assert_eq!(Intcode::new(&program).run_to_halt(), Ok(vec![1125899906842624]));
+  }
+
+  #[test]
+  fn test_err_unknown_opcode() {
+    let mut vm = Intcode::new(&[1101,1,1,5,42,0]);
+    assert_eq!(vm.run_to_halt(), Err(IntcodeError::UnknownOpcode { ip: 4, opcode: 42 }));
+    // Running off the end reads zeroes, which is not an opcode either.
+    let mut vm = Intcode::new(&[1101,1,1,5]);
+    assert_eq!(vm.run_to_halt(), Err(IntcodeError::UnknownOpcode { ip: 4, opcode: 0 }));
+  }
+
+  #[test]
+  fn test_err_unknown_mode() {
+    let mut vm = Intcode::new(&[1301,1,1,5,99]);
+    assert_eq!(vm.step(), Err(IntcodeError::UnknownMode { ip: 0, mode: 3 }));
+  }
+
+  #[test]
+  fn test_err_input_exhausted() {
+    let mut vm = Intcode::new(&[3,0,3,0,99]);
+    vm.push_input(1);
+    assert_eq!(vm.run_to_halt(), Err(IntcodeError::InputExhausted { ip: 2 }));
+  }
+
+  #[test]
+  fn test_err_invalid_address() {
+    let mut vm = Intcode::new(&[1,-1,0,0,99]);
+    assert_eq!(vm.step(), Err(IntcodeError::InvalidAddress { ip: 0, addr: -1 }));
+    assert_eq!(vm.ip(), 0);
+    let mut vm = Intcode::new(&[1105,1,-7]);
+    assert_eq!(vm.step(), Err(IntcodeError::InvalidAddress { ip: 0, addr: -7 }));
+
+    // Past the memory limit, rather than allocating for it.
+    let far = 1_000_000_000_000_000;
+    let mut vm = Intcode::new(&[1101,1,1,far,99]);
+    assert_eq!(vm.step(), Err(IntcodeError::InvalidAddress { ip: 0, addr: far }));
+    let mut vm = Intcode::new(&[1,far,0,0,99]);
+    assert_eq!(vm.step(), Err(IntcodeError::InvalidAddress { ip: 0, addr: far }));
+    let limit = MEMORY_LIMIT as Word;
+    assert_eq!(Intcode::new(&[1101,1,1,limit,99]).run_to_halt(), Ok(vec![]));
+  }
+
+  #[test]
+  fn test_err_overflow() {
+    let mut vm = Intcode::new(&[1101,Word::MAX,1,0,99]);
+    assert_eq!(vm.step(), Err(IntcodeError::Overflow { ip: 0 }));
+    assert_eq!(vm.ip(), 0);
+    let mut vm = Intcode::new(&[1102,Word::MIN,-1,0,99]);
+    assert_eq!(vm.step(), Err(IntcodeError::Overflow { ip: 0 }));
+    let mut vm = Intcode::new(&[109,Word::MAX,109,1,99]);
+    assert_eq!(vm.run_to_halt(), Err(IntcodeError::Overflow { ip: 2 }));
+    let mut vm = Intcode::new(&[109,Word::MAX,204,1,99]);
+    assert_eq!(vm.run_to_halt(), Err(IntcodeError::Overflow { ip: 2 }));
+  }
+
+  #[test]
+  fn test_err_immediate_write() {
+    let mut vm = Intcode::new(&[11101,1,1,5,99]);
+    assert_eq!(vm.step(), Err(IntcodeError::ImmediateWrite { ip: 0 }));
+  }
+
+  #[test]
+  fn test_err_step_limit() {
+    // Spins forever on a jump to itself.
+    let mut vm = Intcode::new(&[1105,1,0]).with_step_limit(10);
+    assert_eq!(vm.run_to_halt(), Err(IntcodeError::StepLimitExceeded { ip: 0, limit: 10 }));
+    assert_eq!(vm.steps(), 10);
+  }
+}
@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use super::{Instruction, Mode, Opcode, Param, Word};
+
+/// Assembles Intcode from mnemonics into the words the VM consumes.
+///
+/// Each line holds at most one instruction or `.data` directive, optionally preceded by labels
+/// (`loop:`) or a numeric address (`12:`, checked against where the line lands, so listings from
+/// `disasm` assemble back unchanged). Operands are `[x]` for position, `#x` for immediate and
+/// `rb+x` for relative mode, where `x` is a number, a label, or a label with an offset
+/// (`[buf+2]`). Everything after `;` is a comment.
+///
+/// ```text
+///         in [n]
+///         mul [n], #2, [n]
+///         out [n]
+///         halt
+/// n:      .data 0
+/// ```
+pub fn assemble(source: &str) -> Result<Vec<Word>, AsmError> {
+  // First pass: parse each line and lay out addresses so labels can be referenced before use.
+  let mut labels: HashMap<String, usize> = HashMap::new();
+  let mut items: Vec<(usize, Item)> = Vec::new();
+  let mut addr = 0;
+  for (n, raw) in source.lines().enumerate() {
+    let line = n + 1;
+    let mut rest = raw.split(';').next().unwrap().trim();
+    while let Some((name, tail)) = split_label(rest) {
+      if let Ok(expected) = name.parse::<usize>() {
+        if expected != addr {
+          return Err(AsmError::new(line, format!("address {} but line is at {}", expected, addr)));
+        }
+      } else if labels.insert(name.to_string(), addr).is_some() {
+        return Err(AsmError::new(line, format!("duplicate label '{}'", name)));
+      }
+      rest = tail.trim_start();
+    }
+    if rest.is_empty() {
+      continue;
+    }
+    let item = parse_item(rest).map_err(|msg| AsmError::new(line, msg))?;
+    addr += item.size();
+    items.push((line, item));
+  }
+
+  // Second pass: resolve labels and emit words.
+  let mut words = Vec::with_capacity(addr);
+  for (line, item) in items {
+    let resolve = |e: &Expr| e.resolve(&labels).map_err(|msg| AsmError::new(line, msg));
+    match item {
+      Item::Inst(op, operands) => {
+        let mut params = Vec::with_capacity(operands.len());
+        for (mode, expr) in operands.iter() {
+          params.push(Param { mode: *mode, value: resolve(expr)? });
+        }
+        words.extend(Instruction::new(op, &params).encode());
+      },
+      Item::Data(exprs) => {
+        for expr in exprs.iter() {
+          words.push(resolve(expr)?);
+        }
+      },
+    }
+  }
+  Ok(words)
+}
+
+/// Assembly failure, pointing at the 1-based source line.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct AsmError {
+  pub line: usize,
+  pub message: String,
+}
+impl AsmError {
+  fn new(line: usize, message: String) -> Self {
+    AsmError { line, message }
+  }
+}
+impl fmt::Display for AsmError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "line {}: {}", self.line, self.message)
+  }
+}
+impl Error for AsmError {}
+
+// DEFINITIONS --------------------
+
+// A parsed line that occupies memory.
+enum Item {
+  Inst(Opcode, Vec<(Mode, Expr)>),
+  Data(Vec<Expr>),
+}
+impl Item {
+  fn size(&self) -> usize {
+    match self {
+      Item::Inst(op, _) => 1 + op.arity(),
+      Item::Data(exprs) => exprs.len(),
+    }
+  }
+}
+
+// A number, or a label plus offset resolved in the second pass.
+enum Expr {
+  Num(Word),
+  Label(String, Word),
+}
+impl Expr {
+  fn parse(s: &str) -> Result<Expr, String> {
+    let s = s.trim();
+    if let Ok(x) = s.parse::<Word>() {
+      return Ok(Expr::Num(x));
+    }
+    let (name, offset) = match s.rfind(&['+', '-'][..]) {
+      Some(k) if k > 0 => {
+        let offset = s[k..].replace('+', "").trim().parse::<Word>()
+          .map_err(|_| format!("bad offset in '{}'", s))?;
+        (s[..k].trim(), offset)
+      },
+      _ => (s, 0),
+    };
+    if !is_ident(name) {
+      return Err(format!("bad operand value '{}'", s));
+    }
+    Ok(Expr::Label(name.to_string(), offset))
+  }
+
+  fn resolve(&self, labels: &HashMap<String, usize>) -> Result<Word, String> {
+    match self {
+      Expr::Num(x) => Ok(*x),
+      Expr::Label(name, offset) => labels
+        .get(name)
+        .map(|&addr| addr as Word + offset)
+        .ok_or_else(|| format!("unknown label '{}'", name)),
+    }
+  }
+}
+
+fn parse_item(s: &str) -> Result<Item, String> {
+  let (head, tail) = match s.find(char::is_whitespace) {
+    Some(k) => (&s[..k], s[k..].trim()),
+    None => (s, ""),
+  };
+  let args: Vec<&str> = if tail.is_empty() { Vec::new() } else { tail.split(',').collect() };
+
+  if head == ".data" {
+    if args.is_empty() {
+      return Err(".data needs at least one word".to_string());
+    }
+    return Ok(Item::Data(args.into_iter().map(Expr::parse).collect::<Result<_, _>>()?));
+  }
+
+  let op = Opcode::ALL
+    .iter()
+    .copied()
+    .find(|op| op.mnemonic() == head)
+    .ok_or_else(|| format!("unknown mnemonic '{}'", head))?;
+  if args.len() != op.arity() {
+    return Err(format!("{} takes {} operands, got {}", head, op.arity(), args.len()));
+  }
+  let operands = args.into_iter().map(parse_operand).collect::<Result<_, _>>()?;
+  Ok(Item::Inst(op, operands))
+}
+
+// Parses `[x]`, `#x` or `rb+x` into its mode and value.
+fn parse_operand(s: &str) -> Result<(Mode, Expr), String> {
+  let s = s.trim();
+  if s.starts_with('[') && s.ends_with(']') {
+    Ok((Mode::POS, Expr::parse(&s[1..s.len() - 1])?))
+  } else if let Some(x) = s.strip_prefix('#') {
+    Ok((Mode::IMM, Expr::parse(x)?))
+  } else if let Some(x) = s.strip_prefix("rb") {
+    let x = x.trim_start();
+    if x.is_empty() {
+      Ok((Mode::REL, Expr::Num(0)))
+    } else if let Some(x) = x.strip_prefix('+') {
+      Ok((Mode::REL, Expr::parse(x)?))
+    } else if x.starts_with('-') {
+      Ok((Mode::REL, Expr::parse(x)?))
+    } else {
+      Err(format!("bad relative operand '{}'", s))
+    }
+  } else {
+    Err(format!("operand '{}' needs a mode: [x], #x or rb+x", s))
+  }
+}
+
+// Splits a leading `name:` off a line.
+fn split_label(s: &str) -> Option<(&str, &str)> {
+  let k = s.find(':')?;
+  let name = s[..k].trim();
+  if is_ident(name) || name.parse::<usize>().is_ok() {
+    Some((name, &s[k + 1..]))
+  } else {
+    None
+  }
+}
+
+fn is_ident(s: &str) -> bool {
+  let mut chars = s.chars();
+  match chars.next() {
+    Some(c) if c.is_ascii_alphabetic() || c == '_' => {},
+    _ => return false,
+  }
+  chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Small programs shared by the tests of several modules.
+#[cfg(test)]
+pub(crate) mod fixtures {
+  use super::assemble;
+  use crate::intcode::{Intcode, Word};
+
+  /// Echoes inputs back, doubled, until a zero is read.
+  pub const DOUBLER: [Word; 17] = [3,15,1006,15,14,1002,15,2,16,4,16,1105,1,0,99,0,0];
+
+  /// Counts down from the input, printing each number.
+  pub const COUNTDOWN: &str = "
+           in [n]
+    loop:  out [n]
+           add [n], #-1, [n]
+           jnz [n], #loop
+           halt
+    n:     .data 0
+  ";
+
+  /// Machine loaded with `COUNTDOWN`.
+  pub fn countdown() -> Intcode {
+    Intcode::new(&assemble(COUNTDOWN).unwrap())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::fixtures::COUNTDOWN;
+  use crate::intcode::disasm::listing;
+  use crate::intcode::Intcode;
+
+  #[test]
+  fn test_assemble_day5_eq() {
+    let program = assemble("
+      in [n]
+      eq [n], [eight], [n]   ; n = n == 8
+      out [n]
+      halt
+      n:     .data -1
+      eight: .data 8
+    ").unwrap();
+    assert_eq!(program, vec![3,9,8,9,10,9,4,9,99,-1,8]);
+  }
+
+  #[test]
+  fn test_assemble_modes_and_offsets() {
+    let program = assemble("
+      start: arb #buf
+             add rb+1, #-2, rb-1
+             jz #0, #start
+      buf:   .data 10, 20, start, buf+1
+    ").unwrap();
+    assert_eq!(program, vec![109,9,21201,1,-2,-1,1106,0,0,10,20,0,10]);
+  }
+
+  #[test]
+  fn test_assemble_runs() {
+    let program = assemble(COUNTDOWN).unwrap();
+    let mut vm = Intcode::new(&program);
+    vm.push_input(3);
+    assert_eq!(vm.run_to_halt(), Ok(vec![3, 2, 1]));
+  }
+
+  #[test]
+  fn test_round_trip() {
+    let programs = vec![
+      vec![3,9,8,9,10,9,4,9,99,-1,8],
+      vec![1101,100,-1,4,0,109,19,204,-34,20099],
+      vec![3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,
+        1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,
+        999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99],
+    ];
+    for program in programs {
+      assert_eq!(assemble(&listing(&program)), Ok(program));
+    }
+  }
+
+  #[test]
+  fn test_errors() {
+    let err = |src: &str| assemble(src).unwrap_err();
+    assert_eq!(err("halt\nfoo [1]"), AsmError::new(2, "unknown mnemonic 'foo'".to_string()));
+    assert_eq!(err("add [1], #2"), AsmError::new(1, "add takes 3 operands, got 2".to_string()));
+    assert_eq!(err("out 5"),
+      AsmError::new(1, "operand '5' needs a mode: [x], #x or rb+x".to_string()));
+    assert_eq!(err("\n\nout [x]"), AsmError::new(3, "unknown label 'x'".to_string()));
+    assert_eq!(err("a: halt\na: halt"), AsmError::new(2, "duplicate label 'a'".to_string()));
+    assert_eq!(err("halt\n3: halt"), AsmError::new(2, "address 3 but line is at 1".to_string()));
+  }
+}
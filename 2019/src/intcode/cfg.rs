@@ -0,0 +1,266 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use super::{decode, Instruction, Mode, Opcode, Word};
+
+/// Control-flow graph of the instructions statically reachable from address 0.
+///
+/// Jumps with an immediate target become edges. Jumps whose target is read from memory can't be
+/// followed without running the program, so their blocks are marked `Target::Indirect` instead.
+/// A jump whose condition is immediate is either always or never taken, and only the edge that
+/// can happen is kept, so `jnz #1, #x` reads as the unconditional jump it is.
+pub struct Cfg {
+  pub blocks: Vec<BasicBlock>,
+  /// Addresses decoded as part of more than one instruction, e.g. a jump into the middle of one.
+  pub overlaps: BTreeSet<usize>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BasicBlock {
+  pub start: usize,
+  pub insts: Vec<(usize, Instruction)>,
+  pub exit: Exit,
+}
+
+/// How control leaves a basic block.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Exit {
+  Halt,
+  /// Runs straight into the block at this address, which is also a jump target.
+  Next(usize),
+  /// Conditional or unconditional jump. `fall` is where a not-taken jump continues, if it can
+  /// be not taken at all.
+  Jump { target: Target, fall: Option<usize> },
+  /// Runs into a word that isn't a valid instruction.
+  Invalid(usize),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Target {
+  Direct(usize),
+  Indirect,
+}
+
+impl Cfg {
+  pub fn new(program: &[Word]) -> Cfg {
+    let (insts, leaders, overlaps) = discover(program);
+
+    let mut blocks = Vec::new();
+    for &start in leaders.iter() {
+      let mut block = BasicBlock { start, insts: Vec::new(), exit: Exit::Invalid(start) };
+      let mut addr = start;
+      while let Some(&inst) = insts.get(&addr) {
+        block.insts.push((addr, inst));
+        addr += inst.size();
+        block.exit = match inst.op {
+          Opcode::Halt => Exit::Halt,
+          Opcode::Jnz | Opcode::Jz => jump_exit(&inst, addr),
+          _ if leaders.contains(&addr) => Exit::Next(addr),
+          _ => Exit::Invalid(addr),
+        };
+        if block.exit != Exit::Invalid(addr) {
+          break;
+        }
+      }
+      blocks.push(block);
+    }
+    Cfg { blocks, overlaps }
+  }
+
+  /// Renders the graph in Graphviz DOT.
+  ///
+  /// Blocks ending in an indirect jump are drawn red with a dashed edge to a shared `?` node, and
+  /// blocks running into undecodable words get an edge to an `invalid` node.
+  pub fn to_dot(&self) -> String {
+    let mut out = String::new();
+    writeln!(out, "digraph intcode {{").unwrap();
+    writeln!(out, "  node [shape=box, fontname=\"monospace\"];").unwrap();
+    let (mut indirect, mut invalid) = (false, false);
+    for block in self.blocks.iter() {
+      let label: String = block.insts
+        .iter()
+        .map(|(addr, inst)| format!("{}: {}\\l", addr, inst))
+        .collect();
+      let color = match block.exit {
+        Exit::Jump { target: Target::Indirect, .. } => ", color=red",
+        _ => "",
+      };
+      writeln!(out, "  b{} [label=\"{}\"{}];", block.start, label, color).unwrap();
+
+      let b = block.start;
+      match block.exit {
+        Exit::Halt => {},
+        Exit::Next(next) => writeln!(out, "  b{} -> b{};", b, next).unwrap(),
+        Exit::Jump { target, fall } => {
+          match target {
+            Target::Direct(t) => writeln!(out, "  b{} -> b{} [label=\"taken\"];", b, t).unwrap(),
+            Target::Indirect => {
+              indirect = true;
+              writeln!(out, "  b{} -> indirect [style=dashed];", b).unwrap();
+            },
+          }
+          if let Some(fall) = fall {
+            writeln!(out, "  b{} -> b{} [label=\"fall\"];", b, fall).unwrap();
+          }
+        },
+        Exit::Invalid(addr) => {
+          invalid = true;
+          writeln!(out, "  b{} -> invalid [label=\"{}\"];", b, addr).unwrap();
+        },
+      }
+    }
+    if indirect {
+      writeln!(out, "  indirect [label=\"?\", shape=diamond, color=red];").unwrap();
+    }
+    if invalid {
+      writeln!(out, "  invalid [shape=octagon];").unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+    out
+  }
+}
+
+// Exit for a jump instruction ending just before `fall`.
+fn jump_exit(inst: &Instruction, fall: usize) -> Exit {
+  let (cond, dest) = (inst.params()[0], inst.params()[1]);
+  let target = match dest.mode {
+    Mode::IMM if dest.value >= 0 => Target::Direct(dest.value as usize),
+    _ => Target::Indirect,
+  };
+  if cond.mode != Mode::IMM {
+    return Exit::Jump { target, fall: Some(fall) };
+  }
+  let taken = match inst.op {
+    Opcode::Jnz => cond.value != 0,
+    _ => cond.value == 0,
+  };
+  if taken {
+    Exit::Jump { target, fall: None }
+  } else {
+    Exit::Next(fall)
+  }
+}
+
+type Discovered = (BTreeMap<usize, Instruction>, BTreeSet<usize>, BTreeSet<usize>);
+
+// Finds every instruction reachable from 0, the addresses that start basic blocks, and any
+// addresses claimed by more than one instruction.
+fn discover(program: &[Word]) -> Discovered {
+  let mut insts = BTreeMap::new();
+  let mut covered = vec![false; program.len()];
+  let mut overlaps = BTreeSet::new();
+  let mut leaders = BTreeSet::new();
+  let mut pending = vec![0];
+  leaders.insert(0);
+
+  let branch = |addr: usize, leaders: &mut BTreeSet<usize>, pending: &mut Vec<usize>| {
+    if addr < program.len() && leaders.insert(addr) {
+      pending.push(addr);
+    }
+  };
+
+  while let Some(mut addr) = pending.pop() {
+    while !insts.contains_key(&addr) {
+      let inst = match decode(program, addr) {
+        Ok(inst) if addr + inst.size() <= program.len() => inst,
+        _ => break,
+      };
+      for (a, word) in covered[addr..addr + inst.size()].iter_mut().enumerate() {
+        if *word {
+          overlaps.insert(addr + a);
+        }
+        *word = true;
+      }
+      insts.insert(addr, inst);
+      let next = addr + inst.size();
+      match inst.op {
+        Opcode::Halt => break,
+        Opcode::Jnz | Opcode::Jz => {
+          match jump_exit(&inst, next) {
+            Exit::Jump { target, fall } => {
+              if let Target::Direct(t) = target {
+                branch(t, &mut leaders, &mut pending);
+              }
+              if let Some(f) = fall {
+                branch(f, &mut leaders, &mut pending);
+              }
+            },
+            _ => branch(next, &mut leaders, &mut pending),
+          }
+          break;
+        },
+        _ => addr = next,
+      }
+    }
+  }
+  (insts, leaders, overlaps)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::intcode::asm::assemble;
+
+  #[test]
+  fn test_blocks() {
+    let program = assemble("
+             in [n]
+      loop:  out [n]
+             add [n], #-1, [n]
+             jnz [n], #loop
+             jnz #1, #done
+             .data 12345
+      done:  halt
+      n:     .data 0
+    ").unwrap();
+    let cfg = Cfg::new(&program);
+    let exits: Vec<(usize, Exit)> = cfg.blocks.iter().map(|b| (b.start, b.exit)).collect();
+    assert_eq!(exits, vec![
+      (0, Exit::Next(2)),
+      (2, Exit::Jump { target: Target::Direct(2), fall: Some(11) }),
+      (11, Exit::Jump { target: Target::Direct(15), fall: None }),
+      (15, Exit::Halt),
+    ]);
+    assert!(cfg.overlaps.is_empty());
+  }
+
+  #[test]
+  fn test_indirect_and_invalid() {
+    let program = assemble("
+             jz [x], [x]
+             .data 42
+      x:     .data 0
+    ").unwrap();
+    let cfg = Cfg::new(&program);
+    assert_eq!(cfg.blocks.len(), 2);
+    assert_eq!(cfg.blocks[0].exit, Exit::Jump { target: Target::Indirect, fall: Some(3) });
+    assert_eq!(cfg.blocks[1].exit, Exit::Invalid(3));
+  }
+
+  #[test]
+  fn test_overlaps() {
+    // Jumps into the middle of its own `add`, whose operand 99 then decodes as `halt`.
+    let program = vec![1101,99,0,6,1105,1,1];
+    assert_eq!(Cfg::new(&program).overlaps, vec![1].into_iter().collect());
+  }
+
+  #[test]
+  fn test_dot() {
+    let program = assemble("
+      loop:  jz [x], #loop
+             jnz #1, [x]
+      x:     .data 0
+    ").unwrap();
+    assert_eq!(Cfg::new(&program).to_dot(), [
+      "digraph intcode {",
+      "  node [shape=box, fontname=\"monospace\"];",
+      "  b0 [label=\"0: jz [6], #0\\l\"];",
+      "  b0 -> b0 [label=\"taken\"];",
+      "  b0 -> b3 [label=\"fall\"];",
+      "  b3 [label=\"3: jnz #1, [6]\\l\", color=red];",
+      "  b3 -> indirect [style=dashed];",
+      "  indirect [label=\"?\", shape=diamond, color=red];",
+      "}",
+      "",
+    ].join("\n"));
+  }
+}
@@ -0,0 +1,402 @@
+use std::collections::{BTreeSet, VecDeque};
+use std::io::{self, BufRead, Write};
+use super::{address, decode, Intcode, IntcodeError, Mode, Status, Word};
+
+/// Max number of steps kept for reverse-stepping.
+const HISTORY_LIMIT: usize = 1_000_000;
+
+/// Line-oriented debugger around an `Intcode` machine.
+///
+/// Commands are read one per line, so a session can be scripted from a string in tests or driven
+/// from stdin:
+///
+/// | command            | effect                                                   |
+/// |--------------------|----------------------------------------------------------|
+/// | `s`, `step [n]`    | execute `n` (default 1) instructions, printing each one  |
+/// | `c`, `continue`    | run until a breakpoint, watchpoint, input wait or halt   |
+/// | `rs`, `back [n]`   | undo the last `n` (default 1) steps                      |
+/// | `b`, `break <a>`   | break before executing address `a`                       |
+/// | `d`, `delete <a>`  | remove the breakpoint at `a`                             |
+/// | `w`, `watch <a>`   | stop whenever memory cell `a` changes                    |
+/// | `unwatch <a>`      | remove the watchpoint on `a`                             |
+/// | `i`, `input <v>..` | queue input values                                       |
+/// | `r`, `regs`        | print the instruction pointer, relative base and steps   |
+/// | `x <a> [n]`        | print `n` (default 1) memory cells starting at `a`       |
+/// | `l`, `list [n]`    | print the next `n` (default 5) instructions from ip      |
+/// | `q`, `quit`        | stop reading commands                                    |
+pub struct Debugger {
+  vm: Intcode,
+  breakpoints: BTreeSet<usize>,
+  watchpoints: BTreeSet<usize>,
+  // What each step changed, most recent last.
+  history: VecDeque<Undo>,
+  outputs: Vec<Word>,
+}
+
+/// Why execution stopped.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Stop {
+  Stepped,
+  Breakpoint(usize),
+  Watchpoint { addr: usize, old: Word, new: Word },
+  NeedsInput,
+  Halted,
+  Error(IntcodeError),
+}
+
+impl Debugger {
+  pub fn new(vm: Intcode) -> Self {
+    Debugger {
+      vm,
+      breakpoints: BTreeSet::new(),
+      watchpoints: BTreeSet::new(),
+      history: VecDeque::new(),
+      outputs: Vec::new(),
+    }
+  }
+
+  pub fn vm(&self) -> &Intcode {
+    &self.vm
+  }
+
+  /// Every value output so far, rewound along with reverse steps.
+  pub fn outputs(&self) -> &[Word] {
+    &self.outputs
+  }
+
+  pub fn add_breakpoint(&mut self, addr: usize) {
+    self.breakpoints.insert(addr);
+  }
+
+  pub fn add_watchpoint(&mut self, addr: usize) {
+    self.watchpoints.insert(addr);
+  }
+
+  /// Executes one instruction, recording what it changes for `back`.
+  pub fn step(&mut self) -> Stop {
+    let watched: Vec<(usize, Word)> =
+      self.watchpoints.iter().map(|&a| (a, self.peek(a))).collect();
+    let mut undo = Undo {
+      ip: self.vm.ip,
+      rb: self.vm.rb,
+      steps: self.vm.steps,
+      halted: self.vm.halted,
+      mem_len: self.vm.mem.len(),
+      write: self.write_target().map(|a| (a, self.peek(a))),
+      input: self.vm.inputs.front().copied(),
+      outputs: self.outputs.len(),
+    };
+    let inputs = self.vm.inputs.len();
+
+    let status = match self.vm.step() {
+      Ok(status) => status,
+      Err(e) => return Stop::Error(e),
+    };
+    match status {
+      Status::NeedsInput => return Stop::NeedsInput,
+      Status::Halted if undo.halted => return Stop::Halted,
+      Status::Output(val) => self.outputs.push(val),
+      _ => {},
+    }
+    if self.vm.inputs.len() == inputs {
+      undo.input = None;
+    }
+    self.history.push_back(undo);
+    if self.history.len() > HISTORY_LIMIT {
+      self.history.pop_front();
+    }
+
+    if let Some(&(addr, old)) = watched.iter().find(|&&(a, old)| self.peek(a) != old) {
+      return Stop::Watchpoint { addr, old, new: self.peek(addr) };
+    }
+    if status == Status::Halted {
+      return Stop::Halted;
+    }
+    Stop::Stepped
+  }
+
+  /// Runs until something other than a plain step happens.
+  ///
+  /// **Note**: Always executes at least one instruction, so continuing from a breakpoint moves
+  /// past it.
+  pub fn resume(&mut self) -> Stop {
+    loop {
+      match self.step() {
+        Stop::Stepped if self.breakpoints.contains(&self.vm.ip()) => {
+          return Stop::Breakpoint(self.vm.ip());
+        },
+        Stop::Stepped => continue,
+        stop => return stop,
+      }
+    }
+  }
+
+  /// Undoes the last step, returning false if there's no history left.
+  pub fn back(&mut self) -> bool {
+    let undo = match self.history.pop_back() {
+      Some(undo) => undo,
+      None => return false,
+    };
+    self.vm.ip = undo.ip;
+    self.vm.rb = undo.rb;
+    self.vm.steps = undo.steps;
+    self.vm.halted = undo.halted;
+    if let Some((addr, old)) = undo.write {
+      if addr < self.vm.mem.len() {
+        self.vm.mem[addr] = old;
+      }
+    }
+    self.vm.mem.truncate(undo.mem_len);
+    if let Some(x) = undo.input {
+      self.vm.inputs.push_front(x);
+    }
+    self.outputs.truncate(undo.outputs);
+    true
+  }
+
+  /// Reads commands from `input` until it runs out or `quit`, writing results to `out`.
+  pub fn repl<R: BufRead, W: Write>(&mut self, input: R, mut out: W) -> io::Result<()> {
+    for line in input.lines() {
+      let line = line?;
+      let words: Vec<&str> = line.split_whitespace().collect();
+      if words.is_empty() {
+        continue;
+      }
+      if words[0] == "q" || words[0] == "quit" {
+        break;
+      }
+      if let Err(msg) = self.command(&words, &mut out) {
+        writeln!(out, "error: {}", msg)?;
+      }
+    }
+    Ok(())
+  }
+
+  // Runs a single command, returning a message for malformed ones.
+  fn command<W: Write>(&mut self, words: &[&str], out: &mut W) -> Result<(), String> {
+    let arg = |k: usize, default: Option<Word>| -> Result<Word, String> {
+      match words.get(k) {
+        Some(s) => s.parse().map_err(|_| format!("not a number: {}", s)),
+        None => default.ok_or_else(|| format!("{} needs an argument", words[0])),
+      }
+    };
+    let io = |e: io::Error| e.to_string();
+
+    match words[0] {
+      "s" | "step" => {
+        for _ in 0..arg(1, Some(1))? {
+          self.print_inst(self.vm.ip(), out).map_err(io)?;
+          let stop = self.step();
+          if stop != Stop::Stepped {
+            self.print_stop(stop, out).map_err(io)?;
+            break;
+          }
+        }
+      },
+      "c" | "continue" => {
+        let stop = self.resume();
+        self.print_stop(stop, out).map_err(io)?;
+      },
+      "rs" | "back" => {
+        for _ in 0..arg(1, Some(1))? {
+          if !self.back() {
+            writeln!(out, "no history").map_err(io)?;
+            break;
+          }
+        }
+        self.print_inst(self.vm.ip(), out).map_err(io)?;
+      },
+      "b" | "break" => { self.breakpoints.insert(addr(arg(1, None)?)?); },
+      "d" | "delete" => { self.breakpoints.remove(&addr(arg(1, None)?)?); },
+      "w" | "watch" => { self.watchpoints.insert(addr(arg(1, None)?)?); },
+      "unwatch" => { self.watchpoints.remove(&addr(arg(1, None)?)?); },
+      "i" | "input" => {
+        for k in 1..words.len() {
+          self.vm.push_input(arg(k, None)?);
+        }
+      },
+      "r" | "regs" => {
+        writeln!(out, "ip={} rb={} steps={}",
+          self.vm.ip(), self.vm.relative_base(), self.vm.steps()).map_err(io)?;
+      },
+      "x" => {
+        let start = addr(arg(1, None)?)?;
+        for a in start..start + addr(arg(2, Some(1))?)? {
+          writeln!(out, "[{}] = {}", a, self.peek(a)).map_err(io)?;
+        }
+      },
+      "l" | "list" => {
+        let mut ip = self.vm.ip();
+        for _ in 0..arg(1, Some(5))? {
+          ip = self.print_inst(ip, out).map_err(io)?;
+        }
+      },
+      cmd => return Err(format!("unknown command: {}", cmd)),
+    }
+    Ok(())
+  }
+
+  // Prints the instruction at `ip`, returning the address of the next one.
+  fn print_inst<W: Write>(&self, ip: usize, out: &mut W) -> io::Result<usize> {
+    let marker = if self.breakpoints.contains(&ip) { '*' } else { ' ' };
+    match decode(self.vm.memory(), ip) {
+      Ok(inst) => {
+        writeln!(out, "{}{:>5}: {}", marker, ip, inst)?;
+        Ok(ip + inst.size())
+      },
+      Err(_) => {
+        writeln!(out, "{}{:>5}: .data {}", marker, ip, self.peek(ip))?;
+        Ok(ip + 1)
+      },
+    }
+  }
+
+  fn print_stop<W: Write>(&self, stop: Stop, out: &mut W) -> io::Result<()> {
+    match stop {
+      Stop::Stepped => Ok(()),
+      Stop::Breakpoint(ip) => writeln!(out, "breakpoint at {}", ip),
+      Stop::Watchpoint { addr, old, new } =>
+        writeln!(out, "watch [{}]: {} -> {} (ip={})", addr, old, new, self.vm.ip()),
+      Stop::NeedsInput => writeln!(out, "needs input at {}", self.vm.ip()),
+      Stop::Halted => writeln!(out, "halted, outputs: {:?}", self.outputs),
+      Stop::Error(e) => writeln!(out, "error: {}", e),
+    }
+  }
+
+  fn peek(&self, addr: usize) -> Word {
+    self.vm.memory().get(addr).copied().unwrap_or(0)
+  }
+
+  // Address the next instruction would write to, if it writes at all.
+  fn write_target(&self) -> Option<usize> {
+    let inst = decode(self.vm.memory(), self.vm.ip()).ok().filter(|inst| inst.op.writes())?;
+    let param = *inst.params().last()?;
+    match param.mode {
+      Mode::POS => address(param.value),
+      Mode::REL => address(self.vm.rb.checked_add(param.value)?),
+      Mode::IMM => None,
+    }
+  }
+}
+
+// What a single step changed: the registers before it, the one memory cell it can overwrite, how
+// long memory was, and the input it consumed.
+struct Undo {
+  ip: usize,
+  rb: Word,
+  steps: usize,
+  halted: bool,
+  mem_len: usize,
+  write: Option<(usize, Word)>,
+  input: Option<Word>,
+  outputs: usize,
+}
+
+fn addr(x: Word) -> Result<usize, String> {
+  if x < 0 {
+    return Err(format!("invalid address {}", x));
+  }
+  Ok(x as usize)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::intcode::asm::fixtures::countdown;
+
+  fn session(script: &str) -> String {
+    let mut out = Vec::new();
+    Debugger::new(countdown()).repl(script.as_bytes(), &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+  }
+
+  #[test]
+  fn test_step_and_regs() {
+    assert_eq!(session("i 2\ns 2\nr\nx 12"), [
+      "     0: in [12]",
+      "     2: out [12]",
+      "ip=4 rb=0 steps=2",
+      "[12] = 2",
+      "",
+    ].join("\n"));
+  }
+
+  #[test]
+  fn test_breakpoint() {
+    assert_eq!(session("b 2\ni 3\nc\nc\nd 2\nc"), [
+      "breakpoint at 2",
+      "breakpoint at 2",
+      "halted, outputs: [3, 2, 1]",
+      "",
+    ].join("\n"));
+  }
+
+  #[test]
+  fn test_watchpoint_and_input_wait() {
+    assert_eq!(session("c\ni 1\nw 12\nc\nc\nc"), [
+      "needs input at 0",
+      "watch [12]: 0 -> 1 (ip=2)",
+      "watch [12]: 1 -> 0 (ip=8)",
+      "halted, outputs: [1]",
+      "",
+    ].join("\n"));
+  }
+
+  #[test]
+  fn test_reverse_step() {
+    let mut dbg = Debugger::new(countdown());
+    dbg.vm.push_input(2);
+    assert_eq!(dbg.resume(), Stop::Halted);
+    assert_eq!(dbg.outputs(), &[2, 1]);
+
+    // Rewind to just before the second `out`.
+    for _ in 0..4 {
+      assert!(dbg.back());
+    }
+    assert_eq!(dbg.vm().ip(), 2);
+    assert_eq!(dbg.vm().memory()[12], 1);
+    assert_eq!(dbg.outputs(), &[2]);
+
+    assert_eq!(dbg.step(), Stop::Stepped);
+    assert_eq!(dbg.outputs(), &[2, 1]);
+  }
+
+  #[test]
+  fn test_reverse_step_restores_state() {
+    // Grows memory through a relative write, then reads an input past the end and prints it.
+    let mut dbg = Debugger::new(Intcode::new(&[109,5, 21101,3,4,10, 3,20, 4,20, 99]));
+    dbg.vm.push_input(9);
+    let state = |vm: &Intcode| {
+      (vm.ip, vm.rb, vm.steps, vm.halted, vm.mem.clone(), vm.inputs.clone())
+    };
+    let mut before = Vec::new();
+    loop {
+      before.push(state(dbg.vm()));
+      if dbg.step() == Stop::Halted {
+        break;
+      }
+    }
+    assert_eq!(dbg.outputs(), &[9]);
+    assert_eq!(dbg.vm().memory().len(), 21);
+
+    while let Some(expected) = before.pop() {
+      assert!(dbg.back());
+      assert_eq!(state(dbg.vm()), expected);
+    }
+    assert!(!dbg.back());
+    assert_eq!(dbg.outputs(), &[] as &[Word]);
+  }
+
+  #[test]
+  fn test_list_and_errors() {
+    assert_eq!(session("l 2\nb\nfoo\nrs"), [
+      "     0: in [12]",
+      "     2: out [12]",
+      "error: b needs an argument",
+      "error: unknown command: foo",
+      "no history",
+      "     0: in [12]",
+      "",
+    ].join("\n"));
+  }
+}
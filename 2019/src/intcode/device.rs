@@ -0,0 +1,284 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{Receiver, Sender};
+use super::{Intcode, IntcodeError, Status, Word};
+
+/// Source of values for `in` instructions.
+pub trait InputDevice {
+  /// Next value to feed the machine, or `None` if there won't be any more.
+  fn read(&mut self) -> Option<Word>;
+}
+
+/// Sink for values produced by `out` instructions.
+pub trait OutputDevice {
+  fn write(&mut self, value: Word);
+}
+
+impl Intcode {
+  /// Runs to completion, reading input from `input` whenever the queue is empty and sending every
+  /// output to `output`.
+  ///
+  /// Fails with `InputExhausted` if the machine needs a value `input` doesn't have.
+  pub fn run_with<I: InputDevice, O: OutputDevice>(
+    &mut self,
+    input: &mut I,
+    output: &mut O,
+  ) -> Result<(), IntcodeError> {
+    loop {
+      match self.run_until_io()? {
+        Status::Output(val) => output.write(val),
+        Status::NeedsInput => match input.read() {
+          Some(val) => self.push_input(val),
+          None => return Err(IntcodeError::InputExhausted { ip: self.ip }),
+        },
+        _ => return Ok(()),
+      }
+    }
+  }
+}
+
+// Fixed queue ----------
+
+impl InputDevice for VecDeque<Word> {
+  fn read(&mut self) -> Option<Word> {
+    self.pop_front()
+  }
+}
+
+impl OutputDevice for Vec<Word> {
+  fn write(&mut self, value: Word) {
+    self.push(value);
+  }
+}
+
+// Channel ----------
+
+/// Blocks until a value arrives, so machines on separate threads can be wired together.
+/// Runs out once every sender has been dropped.
+impl InputDevice for Receiver<Word> {
+  fn read(&mut self) -> Option<Word> {
+    self.recv().ok()
+  }
+}
+
+/// **Note**: Values sent after the receiver is dropped are discarded.
+impl OutputDevice for Sender<Word> {
+  fn write(&mut self, value: Word) {
+    self.send(value).ok();
+  }
+}
+
+// ASCII console ----------
+
+/// Feeds lines of text one character at a time, each line ending in `\n`.
+///
+/// **Note**: A read error ends the input like end of file would, and is kept in `error`.
+pub struct AsciiInput<R> {
+  lines: R,
+  pending: VecDeque<Word>,
+  error: Option<io::Error>,
+}
+
+impl<R: BufRead> AsciiInput<R> {
+  pub fn new(lines: R) -> Self {
+    AsciiInput { lines, pending: VecDeque::new(), error: None }
+  }
+
+  /// The read error that ended the input, if any.
+  pub fn error(&self) -> Option<&io::Error> {
+    self.error.as_ref()
+  }
+}
+
+impl<R: BufRead> InputDevice for AsciiInput<R> {
+  fn read(&mut self) -> Option<Word> {
+    if self.pending.is_empty() {
+      if self.error.is_some() {
+        return None;
+      }
+      let mut line = String::new();
+      match self.lines.read_line(&mut line) {
+        Ok(0) => return None,
+        Ok(_) => {},
+        Err(e) => {
+          self.error = Some(e);
+          return None;
+        },
+      }
+      let line = line.trim_end_matches(&['\r', '\n'][..]);
+      self.pending.extend(line.bytes().map(Word::from));
+      self.pending.push_back(b'\n' as Word);
+    }
+    self.pending.pop_front()
+  }
+}
+
+/// Prints outputs as characters. Values outside ASCII, like the answers ASCII programs report at
+/// the end, are printed as numbers on their own line and also kept in `values`.
+///
+/// **Note**: A write error is kept in `error`, and nothing more is printed after it. Values are
+/// still kept.
+pub struct AsciiOutput<W> {
+  out: W,
+  pub values: Vec<Word>,
+  error: Option<io::Error>,
+}
+
+impl<W: Write> AsciiOutput<W> {
+  pub fn new(out: W) -> Self {
+    AsciiOutput { out, values: Vec::new(), error: None }
+  }
+
+  /// The write error that stopped the printing, if any.
+  pub fn error(&self) -> Option<&io::Error> {
+    self.error.as_ref()
+  }
+
+  pub fn into_inner(self) -> W {
+    self.out
+  }
+}
+
+impl<W: Write> OutputDevice for AsciiOutput<W> {
+  fn write(&mut self, value: Word) {
+    if !(0..=127).contains(&value) {
+      self.values.push(value);
+    }
+    if self.error.is_some() {
+      return;
+    }
+    let written = match value {
+      0..=127 => self.out.write_all(&[value as u8]),
+      _ => writeln!(self.out, "{}", value),
+    };
+    self.error = written.err();
+  }
+}
+
+// Callbacks ----------
+
+/// Input device that calls a closure for each value.
+pub struct InputFn<F>(pub F);
+
+impl<F: FnMut() -> Option<Word>> InputDevice for InputFn<F> {
+  fn read(&mut self) -> Option<Word> {
+    (self.0)()
+  }
+}
+
+/// Output device that calls a closure with each value.
+pub struct OutputFn<F>(pub F);
+
+impl<F: FnMut(Word)> OutputDevice for OutputFn<F> {
+  fn write(&mut self, value: Word) {
+    (self.0)(value)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::intcode::asm::assemble;
+  use std::sync::mpsc::channel;
+  use std::thread;
+
+  // Echoes input back until a zero is read.
+  fn echo() -> Intcode {
+    Intcode::new(&assemble("
+      loop:  in [x]
+             jz [x], #done
+             out [x]
+             jnz #1, #loop
+      done:  halt
+      x:     .data 0
+    ").unwrap())
+  }
+
+  #[test]
+  fn test_queue() {
+    let mut outputs = Vec::new();
+    let mut inputs: VecDeque<Word> = vec![3, 2, 1, 0].into_iter().collect();
+    echo().run_with(&mut inputs, &mut outputs).unwrap();
+    assert_eq!(outputs, vec![3, 2, 1]);
+
+    let mut inputs: VecDeque<Word> = vec![3].into_iter().collect();
+    let err = echo().run_with(&mut inputs, &mut outputs);
+    assert_eq!(err, Err(IntcodeError::InputExhausted { ip: 0 }));
+  }
+
+  #[test]
+  fn test_channel() {
+    let (to_vm, mut vm_in) = channel();
+    let (mut vm_out, from_vm) = channel();
+    let handle = thread::spawn(move || echo().run_with(&mut vm_in, &mut vm_out));
+
+    for x in 1..=3 {
+      to_vm.send(x * 10).unwrap();
+      assert_eq!(from_vm.recv(), Ok(x * 10));
+    }
+    to_vm.send(0).unwrap();
+    assert_eq!(handle.join().unwrap(), Ok(()));
+    assert!(from_vm.recv().is_err());
+  }
+
+  #[test]
+  fn test_ascii() {
+    let mut input = AsciiInput::new("hi\r\nyo\n".as_bytes());
+    let mut output = AsciiOutput::new(Vec::new());
+    let mut vm = echo();
+    assert_eq!(vm.run_with(&mut input, &mut output), Err(IntcodeError::InputExhausted { ip: 0 }));
+
+    output.write(1234);
+    assert_eq!(output.values, vec![1234]);
+    assert_eq!(String::from_utf8(output.into_inner()).unwrap(), "hi\nyo\n1234\n");
+  }
+
+  #[test]
+  fn test_ascii_errors() {
+    // Reads "a\n" or takes one write, then fails every time.
+    struct Broken(bool);
+    impl io::Read for Broken {
+      fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.0 {
+          return Err(io::Error::new(io::ErrorKind::ConnectionReset, "gone"));
+        }
+        self.0 = true;
+        buf[..2].copy_from_slice(b"a\n");
+        Ok(2)
+      }
+    }
+    impl Write for Broken {
+      fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        io::Read::read(self, &mut [0; 2])?;
+        Ok(buf.len())
+      }
+      fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+      }
+    }
+
+    let mut input = AsciiInput::new(io::BufReader::new(Broken(false)));
+    assert!(input.error().is_none());
+    assert_eq!((input.read(), input.read(), input.read()), (Some(97), Some(10), None));
+    assert_eq!(input.error().unwrap().kind(), io::ErrorKind::ConnectionReset);
+    assert_eq!(input.read(), None);
+
+    let mut output = AsciiOutput::new(Broken(false));
+    output.write(b'a' as Word);
+    assert!(output.error().is_none());
+    output.write(b'b' as Word);
+    output.write(1234);
+    assert_eq!(output.error().unwrap().kind(), io::ErrorKind::ConnectionReset);
+    assert_eq!(output.values, vec![1234]);
+  }
+
+  #[test]
+  fn test_callbacks() {
+    let mut next = 5;
+    let mut input = InputFn(|| { next -= 1; Some(next) });
+    let mut sum = 0;
+    let mut output = OutputFn(|x| sum += x);
+    echo().run_with(&mut input, &mut output).unwrap();
+    assert_eq!(sum, 4 + 3 + 2 + 1);
+  }
+}
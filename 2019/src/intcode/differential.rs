@@ -0,0 +1,208 @@
+// Differential tests between every Intcode implementation in the crate, on generated programs.
+//
+// Proptest saves the seeds of failing cases under `proptest-regressions/` and replays them first
+// on later runs, so check those files in with the fix. Failures worth reading as code go into
+// `FIXTURES` once minimized.
+
+use proptest::prelude::*;
+use crate::{day2, day5};
+use super::debugger::{Debugger, Stop};
+use super::jit::{self, Compiled};
+use super::{Instruction, Intcode, IntcodeError, Mode, Opcode, Param, Word};
+
+/// Programs that once disagreed or that the generator can't produce.
+const FIXTURES: &[&[Word]] = &[
+  // Writes past the end of the program, growing memory.
+  &[1101,2,3,10,4,10,99],
+  // Day 2 style, overwriting its own operands.
+  &[1,0,0,0,99],
+  &[2,4,4,5,99,0],
+  // Turns its second add into a mul before reaching it.
+  &[1101,1101,1,4,1101,6,7,11,4,11,99,0],
+  // Jumps past the end, where memory reads as 0.
+  &[1105,1,100],
+  &[1,-1,0,0,99],
+  &[1101,1,1,5,42,0],
+];
+
+// Outputs and final memory of a successful run.
+type Outcome = Result<(Vec<Word>, Vec<Word>), IntcodeError>;
+
+fn interpreted(program: &[Word]) -> Outcome {
+  let mut vm = Intcode::new(program);
+  let outputs = vm.run_to_halt()?;
+  Ok((outputs, vm.memory().to_vec()))
+}
+
+fn via_day5(program: &[Word]) -> Outcome {
+  let mut n = program.to_vec();
+  let outputs = day5::run_mult_args(&mut n, vec![])?;
+  Ok((outputs, n))
+}
+
+fn via_jit(program: &[Word]) -> Outcome {
+  let mut n = program.to_vec();
+  let outputs = jit::run_mult_args(&mut n, vec![])?;
+  Ok((outputs, n))
+}
+
+fn via_debugger(program: &[Word]) -> Outcome {
+  let mut dbg = Debugger::new(Intcode::new(program));
+  match dbg.resume() {
+    Stop::Halted => Ok((dbg.outputs().to_vec(), dbg.vm().memory().to_vec())),
+    Stop::NeedsInput => Err(IntcodeError::InputExhausted { ip: dbg.vm().ip() }),
+    Stop::Error(e) => Err(e),
+    stop => panic!("unexpected stop {:?}", stop),
+  }
+}
+
+// Checks that every implementation agrees with the interpreter on `program`.
+fn check(program: &[Word]) -> Result<(), TestCaseError> {
+  let expected = interpreted(program);
+  prop_assert_eq!(&via_day5(program), &expected, "day5");
+  prop_assert_eq!(&via_jit(program), &expected, "jit");
+  prop_assert_eq!(&via_debugger(program), &expected, "debugger");
+  if program.len() >= 3 {
+    let mem0 = expected.map(|(_, mem)| mem[0]);
+    prop_assert_eq!(day2::run(program, program[1], program[2]), mem0, "day2");
+  }
+  Ok(())
+}
+
+// GENERATORS --------------------
+
+// Operand of a generated instruction. Positions are taken modulo the program length.
+#[derive(Clone, Debug)]
+enum Src {
+  Imm(Word),
+  Pos(usize),
+}
+
+// Generated instruction. Writes go to the data after `halt`, and jumps skip forward some number
+// of instructions, so every program terminates. `mul` always has a small immediate factor, so
+// values can't overflow.
+#[derive(Clone, Debug)]
+enum Gen {
+  Add(Src, Src, usize),
+  Mul(Src, Word, usize),
+  Jump { op: Opcode, cond: Src, skip: usize },
+  Out(Src),
+}
+
+fn src() -> impl Strategy<Value = Src> {
+  prop_oneof![(-20..20 as Word).prop_map(Src::Imm), any::<usize>().prop_map(Src::Pos)]
+}
+
+fn gen() -> impl Strategy<Value = Gen> {
+  let jump = prop_oneof![Just(Opcode::Jnz), Just(Opcode::Jz)];
+  prop_oneof![
+    (src(), src(), any::<usize>()).prop_map(|(a, b, d)| Gen::Add(a, b, d)),
+    (src(), -9..10 as Word, any::<usize>()).prop_map(|(a, k, d)| Gen::Mul(a, k, d)),
+    (jump, src(), 0..4usize).prop_map(|(op, cond, skip)| Gen::Jump { op, cond, skip }),
+    src().prop_map(Gen::Out),
+  ]
+}
+
+/// Terminating programs of `add`, `mul`, `jnz`, `jz` and `out` with a few words of data.
+fn program() -> impl Strategy<Value = Vec<Word>> {
+  let insts = prop::collection::vec(gen(), 1..12);
+  let data = prop::collection::vec(-20..20 as Word, 1..6);
+  (insts, data).prop_map(|(insts, data)| build(&insts, &data))
+}
+
+fn build(insts: &[Gen], data: &[Word]) -> Vec<Word> {
+  let size = |g: &Gen| match g {
+    Gen::Add(..) | Gen::Mul(..) => 4,
+    Gen::Jump { .. } => 3,
+    Gen::Out(_) => 2,
+  };
+  // Address of each instruction, then of the final `halt`.
+  let mut addrs = vec![0];
+  for g in insts {
+    addrs.push(addrs.last().unwrap() + size(g));
+  }
+  let data_start = addrs.last().unwrap() + 1;
+  let len = data_start + data.len();
+
+  let param = |s: &Src| match *s {
+    Src::Imm(value) => Param { mode: Mode::IMM, value },
+    Src::Pos(p) => Param { mode: Mode::POS, value: (p % len) as Word },
+  };
+  let imm = |value: Word| Param { mode: Mode::IMM, value };
+  let dest = |d: usize| Param { mode: Mode::POS, value: (data_start + d % data.len()) as Word };
+
+  let mut program = Vec::new();
+  for (k, g) in insts.iter().enumerate() {
+    let inst = match g {
+      Gen::Add(a, b, d) => Instruction::new(Opcode::Add, &[param(a), param(b), dest(*d)]),
+      Gen::Mul(a, x, d) => Instruction::new(Opcode::Mul, &[param(a), imm(*x), dest(*d)]),
+      Gen::Jump { op, cond, skip } => {
+        let target = addrs[(k + 1 + skip).min(insts.len())];
+        Instruction::new(*op, &[param(cond), imm(target as Word)])
+      },
+      Gen::Out(s) => Instruction::new(Opcode::Out, &[param(s)]),
+    };
+    program.extend(inst.encode());
+  }
+  program.push(Opcode::Halt.code());
+  program.extend(data);
+  program
+}
+
+/// Day 2 style programs: position mode `add` and `mul` reading the noun, verb, constants or
+/// scratch cells, and writing scratch cells or address 0.
+fn day2_program() -> impl Strategy<Value = Vec<Word>> {
+  // Indices into the readable cells: noun, verb, then 4 constants, then 4 scratch cells.
+  let inst = (any::<bool>(), 0..10usize, 0..6usize, 0..5usize);
+  let consts = prop::collection::vec(0..10 as Word, 4);
+  (prop::collection::vec(inst, 1..7), consts).prop_map(|(insts, consts)| {
+    let base = insts.len() * 4 + 1;
+    let cell = |k: usize| if k < 2 { k as Word + 1 } else { (base + k - 2) as Word };
+    let mut program = Vec::new();
+    for (mul, a, b, d) in insts {
+      // Multiplying by a noun, verb or constant keeps the values in range.
+      let op = if mul { Opcode::Mul } else { Opcode::Add };
+      let dest = if d == 0 { 0 } else { cell(5 + d) };
+      program.extend(&[op.code(), cell(a), cell(b), dest]);
+    }
+    program.push(Opcode::Halt.code());
+    program.extend(consts);
+    program.extend(&[0; 4]);
+    program
+  })
+}
+
+#[test]
+fn test_fixtures() {
+  for program in FIXTURES {
+    if let Err(e) = check(program) {
+      panic!("{:?}: {}", program, e);
+    }
+  }
+}
+
+proptest! {
+  #[test]
+  fn test_implementations_agree(program in program()) {
+    check(&program)?;
+  }
+
+  #[test]
+  fn test_generated_programs_compile(program in program()) {
+    // Nothing writes to code, so the compiled blocks run it all.
+    let run = Compiled::new(&program).unwrap().run(&program, &[]).unwrap();
+    prop_assert_eq!(run.fallback, None);
+  }
+
+  #[test]
+  fn test_symbolic_matches_runs(
+    program in day2_program(),
+    noun in 0..100 as Word,
+    verb in 0..100 as Word,
+  ) {
+    check(&program)?;
+    if let Some(e) = day2::symbolic(&program) {
+      prop_assert_eq!(day2::run(&program, noun, verb), Ok(e.c + e.noun * noun + e.verb * verb));
+    }
+  }
+}
@@ -0,0 +1,145 @@
+use std::fmt;
+use super::{decode, Instruction, Mode, Param, Word};
+
+/// Max number of words printed on a single `.data` line.
+const DATA_PER_LINE: usize = 8;
+
+/// One line of a listing, either a decoded instruction or a run of words that aren't one.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Line {
+  Inst { addr: usize, inst: Instruction },
+  Data { addr: usize, words: Vec<Word> },
+}
+impl Line {
+  pub fn addr(&self) -> usize {
+    match self {
+      Line::Inst { addr, .. } | Line::Data { addr, .. } => *addr,
+    }
+  }
+}
+impl fmt::Display for Line {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Line::Inst { addr, inst } => write!(f, "{:>5}: {}", addr, inst),
+      Line::Data { addr, words } => {
+        let words: Vec<String> = words.iter().map(|w| w.to_string()).collect();
+        write!(f, "{:>5}: .data {}", addr, words.join(", "))
+      },
+    }
+  }
+}
+
+impl fmt::Display for Param {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self.mode {
+      Mode::POS => write!(f, "[{}]", self.value),
+      Mode::IMM => write!(f, "#{}", self.value),
+      Mode::REL if self.value < 0 => write!(f, "rb{}", self.value),
+      Mode::REL => write!(f, "rb+{}", self.value),
+    }
+  }
+}
+
+impl fmt::Display for Instruction {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let params: Vec<String> = self.params().iter().map(|p| p.to_string()).collect();
+    if params.is_empty() {
+      write!(f, "{}", self.op.mnemonic())
+    } else {
+      write!(f, "{} {}", self.op.mnemonic(), params.join(", "))
+    }
+  }
+}
+
+/// Linearly sweeps `program`, decoding an instruction wherever one fits.
+///
+/// Words that don't decode, or would decode into something that doesn't re-encode to the same
+/// words (e.g. stray mode digits on `halt`), are grouped into `.data` lines.
+pub fn disassemble(program: &[Word]) -> Vec<Line> {
+  let mut lines = Vec::new();
+  let mut data: Vec<Word> = Vec::new();
+  let mut addr = 0;
+  while addr < program.len() {
+    match decode_exact(program, addr) {
+      Some(inst) => {
+        flush(&mut lines, &mut data, addr);
+        lines.push(Line::Inst { addr, inst });
+        addr += inst.size();
+      },
+      None => {
+        data.push(program[addr]);
+        if data.len() == DATA_PER_LINE {
+          flush(&mut lines, &mut data, addr + 1);
+        }
+        addr += 1;
+      },
+    }
+  }
+  flush(&mut lines, &mut data, addr);
+  lines
+}
+
+/// Formats `program` as a listing with one line per instruction.
+pub fn listing(program: &[Word]) -> String {
+  disassemble(program)
+    .iter()
+    .map(|line| format!("{}\n", line))
+    .collect()
+}
+
+// Decodes the instruction at `addr` only if it fits in `program` and round-trips exactly.
+fn decode_exact(program: &[Word], addr: usize) -> Option<Instruction> {
+  let inst = decode(program, addr).ok()?;
+  let end = addr + inst.size();
+  if end <= program.len() && inst.encode() == program[addr..end] {
+    Some(inst)
+  } else {
+    None
+  }
+}
+
+// Moves pending data words ending just before `end` into a `.data` line.
+fn flush(lines: &mut Vec<Line>, data: &mut Vec<Word>, end: usize) {
+  if !data.is_empty() {
+    lines.push(Line::Data { addr: end - data.len(), words: data.split_off(0) });
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_listing_day5() {
+    let program = vec![3,9,8,9,10,9,4,9,99,-1,8];
+    assert_eq!(listing(&program), [
+      "    0: in [9]",
+      "    2: eq [9], [10], [9]",
+      "    6: out [9]",
+      "    8: halt",
+      "    9: .data -1, 8",
+      "",
+    ].join("\n"));
+  }
+
+  #[test]
+  fn test_listing_modes() {
+    let program = vec![1101,100,-1,4,0,109,19,204,-34,20099];
+    assert_eq!(listing(&program), [
+      "    0: add #100, #-1, [4]",
+      "    4: .data 0",
+      "    5: arb #19",
+      "    7: out rb-34",
+      "    9: .data 20099",
+      "",
+    ].join("\n"));
+  }
+
+  #[test]
+  fn test_data_wraps() {
+    let program = vec![0; 10];
+    let lines = disassemble(&program);
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1], Line::Data { addr: 8, words: vec![0, 0] });
+  }
+}
@@ -0,0 +1,398 @@
+use std::collections::VecDeque;
+use std::fmt;
+use super::cfg::{Cfg, Exit};
+use super::{address, Instruction, Intcode, IntcodeError, Mode, Opcode, Word};
+
+/// A program decoded once into basic blocks of pre-dispatched closures.
+///
+/// Opcodes and parameter modes are resolved at compile time, so running skips the per-step
+/// decoding the interpreter does. Parameter words are still read from memory when executed, so
+/// patching operands (like day 2's noun and verb) doesn't invalidate anything. Anything the blocks
+/// can't handle hands the machine state over to the `Intcode` interpreter, which finishes the run:
+///
+/// - an instruction whose opcode word has been overwritten (self-modifying code),
+/// - a jump to an address that isn't the start of a compiled block,
+/// - input running out, or any error the interpreter would report.
+pub struct Compiled {
+  program: Vec<Word>,
+  blocks: Vec<Block>,
+  // Block index for each address that starts one.
+  block_at: Vec<Option<usize>>,
+  // Whether each address holds the opcode word of a compiled instruction.
+  opcode_words: Vec<bool>,
+}
+
+/// Outcome of running compiled code.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Run {
+  pub outputs: Vec<Word>,
+  pub memory: Vec<Word>,
+  /// Address where execution was handed to the interpreter, if it was.
+  pub fallback: Option<usize>,
+}
+
+impl Compiled {
+  /// Decodes every instruction reachable from address 0.
+  ///
+  /// Returns `None` if reachable instructions overlap each other, since there's no single
+  /// decoding of those words to compile.
+  pub fn new(program: &[Word]) -> Option<Compiled> {
+    let cfg = Cfg::new(program);
+    if !cfg.overlaps.is_empty() {
+      return None;
+    }
+
+    let mut opcode_words = vec![false; program.len()];
+    let mut blocks = Vec::new();
+    let mut block_at = vec![None; program.len()];
+    for b in cfg.blocks {
+      // Undecodable words are left for the interpreter to report.
+      let next = match b.exit {
+        Exit::Halt => None,
+        Exit::Next(addr) | Exit::Invalid(addr) => Some(addr),
+        Exit::Jump { fall, .. } => fall,
+      };
+      let ops = b.insts.iter().map(|&(addr, inst)| compile_op(addr, inst)).collect();
+      for &(addr, _) in b.insts.iter() {
+        opcode_words[addr] = true;
+      }
+      block_at[b.start] = Some(blocks.len());
+      blocks.push(Block { start: b.start, insts: b.insts, ops, next });
+    }
+
+    Some(Compiled { program: program.to_vec(), blocks, block_at, opcode_words })
+  }
+
+  /// Runs the compiled code against `memory`, which should be the compiled program with at most
+  /// some words patched, with `inputs` queued.
+  pub fn run(&self, memory: &[Word], inputs: &[Word]) -> Result<Run, IntcodeError> {
+    let mut m = Machine {
+      mem: memory.to_vec(),
+      rb: 0,
+      inputs: inputs.iter().copied().collect(),
+      outputs: Vec::new(),
+      dirty: vec![false; self.program.len()],
+      opcode_words: &self.opcode_words,
+    };
+    for (addr, &word) in self.program.iter().enumerate() {
+      if self.opcode_words[addr] && m.mem.get(addr) != Some(&word) {
+        m.dirty[addr] = true;
+      }
+    }
+
+    let mut ip = 0;
+    'blocks: loop {
+      let block = match self.block_at.get(ip).copied().flatten() {
+        Some(k) => &self.blocks[k],
+        None => return m.fallback(ip),
+      };
+      for ((addr, _), op) in block.insts.iter().zip(block.ops.iter()) {
+        if m.dirty[*addr] {
+          return m.fallback(*addr);
+        }
+        match op(&mut m) {
+          Flow::Next => {},
+          Flow::Jump(target) => { ip = target; continue 'blocks; },
+          Flow::Halt => return Ok(Run { outputs: m.outputs, memory: m.mem, fallback: None }),
+          Flow::Fallback => return m.fallback(*addr),
+        }
+      }
+      ip = match block.next {
+        Some(next) => next,
+        None => {
+          let end = block.insts.last().map_or(block.start, |&(a, inst)| a + inst.size());
+          return m.fallback(end);
+        },
+      };
+    }
+  }
+}
+
+/// Lists the basic blocks, one instruction per line.
+impl fmt::Display for Compiled {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for block in self.blocks.iter() {
+      writeln!(f, "block_{}:", block.start)?;
+      for (addr, inst) in block.insts.iter() {
+        writeln!(f, "  {:>5}: {}", addr, inst)?;
+      }
+      if let Some(next) = block.next {
+        writeln!(f, "  -> block_{}", next)?;
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Same as `day5::run_mult_args`, but through compiled code when the program allows it.
+///
+/// **Note**: `n` is only updated if the program succeeds.
+pub fn run_mult_args(n: &mut Vec<Word>, args: Vec<Word>) -> Result<Vec<Word>, IntcodeError> {
+  match Compiled::new(n) {
+    Some(compiled) => {
+      let run = compiled.run(n, &args)?;
+      *n = run.memory;
+      Ok(run.outputs)
+    },
+    None => crate::day5::run_mult_args(n, args),
+  }
+}
+
+// DEFINITIONS --------------------
+
+struct Block {
+  start: usize,
+  insts: Vec<(usize, Instruction)>,
+  ops: Vec<Op>,
+  // Address to continue at after the last instruction, unless it halts or jumped.
+  next: Option<usize>,
+}
+
+type Op = Box<dyn Fn(&mut Machine) -> Flow>;
+
+enum Flow {
+  Next,
+  Jump(usize),
+  Halt,
+  Fallback,
+}
+
+struct Machine<'a> {
+  mem: Vec<Word>,
+  rb: Word,
+  inputs: VecDeque<Word>,
+  outputs: Vec<Word>,
+  // Opcode words overwritten with a different value since compiling.
+  dirty: Vec<bool>,
+  opcode_words: &'a [bool],
+}
+impl<'a> Machine<'a> {
+  fn read(&self, addr: usize) -> Word {
+    self.mem.get(addr).copied().unwrap_or(0)
+  }
+
+  // Value of the parameter word at `p` under mode `m`, or `None` if the address is invalid.
+  fn arg(&self, p: usize, m: Mode) -> Option<Word> {
+    let x = self.read(p);
+    match m {
+      Mode::POS => Some(self.read(address(x)?)),
+      Mode::IMM => Some(x),
+      Mode::REL => Some(self.read(address(self.rb.checked_add(x)?)?)),
+    }
+  }
+
+  // Writes through the parameter word at `p`, or returns `None` if the interpreter should.
+  fn write(&mut self, p: usize, m: Mode, val: Word) -> Option<()> {
+    let x = self.read(p);
+    let addr = match m {
+      Mode::POS => address(x)?,
+      Mode::REL => address(self.rb.checked_add(x)?)?,
+      Mode::IMM => return None,
+    };
+    if addr < self.opcode_words.len() && self.opcode_words[addr] && self.read(addr) != val {
+      self.dirty[addr] = true;
+    }
+    if addr >= self.mem.len() {
+      self.mem.resize(addr + 1, 0);
+    }
+    self.mem[addr] = val;
+    Some(())
+  }
+
+  // Hands the current state to the interpreter at `ip` and runs it to completion.
+  fn fallback(self, ip: usize) -> Result<Run, IntcodeError> {
+    let mut vm = Intcode::new(&self.mem);
+    vm.ip = ip;
+    vm.rb = self.rb;
+    vm.inputs = self.inputs;
+    let mut outputs = self.outputs;
+    outputs.extend(vm.run_to_halt()?);
+    Ok(Run { outputs, memory: vm.mem, fallback: Some(ip) })
+  }
+}
+
+// Builds the closure for one instruction, with its opcode and modes already dispatched.
+fn compile_op(addr: usize, inst: Instruction) -> Op {
+  let (p1, p2, p3) = (addr + 1, addr + 2, addr + 3);
+  let modes: Vec<Mode> = inst.params().iter().map(|p| p.mode).collect();
+  let m = |k: usize| modes[k];
+
+  macro_rules! binary {
+    ($f:expr) => {{
+      let (m1, m2, m3) = (m(0), m(1), m(2));
+      Box::new(move |vm: &mut Machine| {
+        let r = vm.arg(p1, m1).and_then(|a| vm.arg(p2, m2).and_then(|b| $f(a, b)));
+        match r.and_then(|r| vm.write(p3, m3, r)) {
+          Some(()) => Flow::Next,
+          None => Flow::Fallback,
+        }
+      })
+    }};
+  }
+  macro_rules! jump {
+    ($taken:expr) => {{
+      let (m1, m2) = (m(0), m(1));
+      Box::new(move |vm: &mut Machine| {
+        match (vm.arg(p1, m1), vm.arg(p2, m2)) {
+          (Some(cond), Some(target)) if address(target).is_some() => {
+            if $taken(cond) { Flow::Jump(target as usize) } else { Flow::Next }
+          },
+          _ => Flow::Fallback,
+        }
+      })
+    }};
+  }
+
+  match inst.op {
+    Opcode::Add => binary!(|a: Word, b: Word| a.checked_add(b)),
+    Opcode::Mul => binary!(|a: Word, b: Word| a.checked_mul(b)),
+    Opcode::Lt => binary!(|a: Word, b: Word| Some(if a < b { 1 } else { 0 })),
+    Opcode::Eq => binary!(|a: Word, b: Word| Some(if a == b { 1 } else { 0 })),
+    Opcode::Jnz => jump!(|c: Word| c != 0),
+    Opcode::Jz => jump!(|c: Word| c == 0),
+    Opcode::In => {
+      let m1 = m(0);
+      Box::new(move |vm: &mut Machine| {
+        match vm.inputs.front().copied() {
+          Some(x) if vm.write(p1, m1, x).is_some() => {
+            vm.inputs.pop_front();
+            Flow::Next
+          },
+          _ => Flow::Fallback,
+        }
+      })
+    },
+    Opcode::Out => {
+      let m1 = m(0);
+      Box::new(move |vm: &mut Machine| {
+        match vm.arg(p1, m1) {
+          Some(x) => { vm.outputs.push(x); Flow::Next },
+          None => Flow::Fallback,
+        }
+      })
+    },
+    Opcode::Arb => {
+      let m1 = m(0);
+      Box::new(move |vm: &mut Machine| {
+        match vm.arg(p1, m1).and_then(|x| vm.rb.checked_add(x)) {
+          Some(rb) => { vm.rb = rb; Flow::Next },
+          None => Flow::Fallback,
+        }
+      })
+    },
+    Opcode::Halt => Box::new(|_: &mut Machine| Flow::Halt),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::day5;
+  use crate::intcode::asm::assemble;
+
+  // Runs both implementations and checks they agree on outputs and final memory.
+  fn check(program: &[Word], args: &[Word]) -> Vec<Word> {
+    let mut interp = program.to_vec();
+    let mut jit = program.to_vec();
+    let expected = day5::run_mult_args(&mut interp, args.to_vec());
+    let actual = run_mult_args(&mut jit, args.to_vec());
+    assert_eq!(actual, expected, "outputs differ for {:?} with {:?}", program, args);
+    if actual.is_ok() {
+      assert_eq!(jit, interp, "memory differs for {:?} with {:?}", program, args);
+    }
+    actual.unwrap_or_default()
+  }
+
+  #[test]
+  fn test_differential_day5() {
+    let long = vec![3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,
+      1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,
+      999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99];
+    for x in 0..12 {
+      check(&long, &[x]);
+      check(&[3,9,8,9,10,9,4,9,99,-1,8], &[x]);
+      check(&[3,3,1107,-1,8,3,4,3,99], &[x]);
+      check(&[3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9], &[x]);
+      check(&[3,3,1105,-1,9,1101,0,0,12,4,12,99,1], &[x]);
+    }
+  }
+
+  #[test]
+  fn test_differential_day9() {
+    let quine = vec![109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99];
+    assert_eq!(check(&quine, &[]), quine);
+    check(&[1102,34915192,34915192,7,4,7,99,0], &[]);
+  }
+
+  #[test]
+  fn test_differential_errors() {
+    check(&[3,0,3,0,99], &[1]);
+    check(&[1101,1,1,5,42,0], &[]);
+    check(&[1,-1,0,0,99], &[]);
+  }
+
+  #[test]
+  fn test_differential_limits() {
+    let far = 1_000_000_000_000_000;
+    check(&[1101,1,1,far,99], &[]);
+    check(&[4,far,99], &[]);
+    check(&[109,far,204,0,99], &[]);
+    check(&[1105,1,far], &[]);
+    check(&[1101,Word::MAX,1,0,99], &[]);
+    check(&[1102,Word::MIN,-1,0,99], &[]);
+    check(&[109,Word::MAX,109,1,99], &[]);
+    let err = run_mult_args(&mut vec![1101,Word::MAX,1,0,99], vec![]);
+    assert_eq!(err, Err(IntcodeError::Overflow { ip: 0 }));
+    let err = run_mult_args(&mut vec![1101,1,1,far,99], vec![]);
+    assert_eq!(err, Err(IntcodeError::InvalidAddress { ip: 0, addr: far }));
+  }
+
+  #[test]
+  fn test_day2_patched_operands() {
+    let program = vec![1,9,10,3,2,3,11,0,99,30,40,50];
+    let compiled = Compiled::new(&program).unwrap();
+    let run = compiled.run(&program, &[]).unwrap();
+    assert_eq!(run.memory[0], 3500);
+    assert_eq!(run.fallback, None);
+
+    // Point the first add at different cells without recompiling.
+    let mut patched = program.clone();
+    patched[1] = 11;
+    patched[2] = 11;
+    let run = compiled.run(&patched, &[]).unwrap();
+    assert_eq!(run.memory[0], 100 * 50);
+    assert_eq!(run.fallback, None);
+  }
+
+  #[test]
+  fn test_self_modifying_falls_back() {
+    // Turns the second add into a mul before reaching it.
+    let program = assemble("
+             add #1101, #1, [patch]
+      patch: add #6, #7, [x]
+             out [x]
+             halt
+      x:     .data 0
+    ").unwrap();
+    let run = Compiled::new(&program).unwrap().run(&program, &[]).unwrap();
+    assert_eq!(run.outputs, vec![42]);
+    assert_eq!(run.fallback, Some(4));
+    assert_eq!(check(&program, &[]), vec![42]);
+  }
+
+  #[test]
+  fn test_listing() {
+    // The `halt` is unreachable after an unconditional jump, so it isn't compiled.
+    let program = assemble("
+      loop:  out #1
+             jnz #1, #loop
+             halt
+    ").unwrap();
+    assert_eq!(Compiled::new(&program).unwrap().to_string(), [
+      "block_0:",
+      "      0: out #1",
+      "      2: jnz #1, #0",
+      "",
+    ].join("\n"));
+  }
+}
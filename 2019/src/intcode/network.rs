@@ -0,0 +1,222 @@
+use std::collections::VecDeque;
+use super::{Intcode, IntcodeError, Status, Word};
+
+/// Address of the NAT, which packets can be sent to even when it isn't enabled.
+pub const NAT: usize = 255;
+
+/// A packet as written by a machine: three outputs `dest, x, y`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Packet {
+  pub src: usize,
+  pub dest: usize,
+  pub x: Word,
+  pub y: Word,
+}
+
+/// Machines running the same program, booted with their address as the first input and sending
+/// packets to each other by address.
+///
+/// Machines are run one at a time by a cooperative scheduler, so runs are deterministic. In each
+/// `tick`, every machine runs until it wants input it doesn't have:
+///
+/// - A waiting packet is fed as its `x` then `y`.
+/// - With an empty queue it reads `-1` once per tick; asking again ends its turn.
+///
+/// Packets are delivered as soon as all three words are written, so later machines in the same
+/// tick may already see them. The network is idle after a tick in which nothing was sent or
+/// received. With the NAT enabled, it then sends the last packet it got to address 0.
+pub struct Network {
+  nodes: Vec<Node>,
+  nat: Option<Option<Packet>>,
+  idle: bool,
+}
+
+struct Node {
+  vm: Intcode,
+  queue: VecDeque<(Word, Word)>,
+  out: Vec<Word>,
+}
+
+impl Network {
+  /// Boots `size` copies of `program` with addresses `0..size`.
+  pub fn new(program: &[Word], size: usize) -> Self {
+    let nodes = (0..size).map(|addr| {
+      let mut vm = Intcode::new(program);
+      vm.push_input(addr as Word);
+      Node { vm, queue: VecDeque::new(), out: Vec::new() }
+    });
+    Network { nodes: nodes.collect(), nat: None, idle: false }
+  }
+
+  /// Enables the NAT at address 255.
+  pub fn with_nat(mut self) -> Self {
+    self.nat = Some(None);
+    self
+  }
+
+  pub fn node(&self, addr: usize) -> &Intcode {
+    &self.nodes[addr].vm
+  }
+
+  /// Last packet the NAT received, if it's enabled.
+  pub fn nat_packet(&self) -> Option<Packet> {
+    self.nat.flatten()
+  }
+
+  /// Whether nothing was sent or received during the last tick.
+  pub fn is_idle(&self) -> bool {
+    self.idle
+  }
+
+  /// Gives every machine one turn, returning the packets sent in order, including one from the
+  /// NAT if it woke the network up.
+  ///
+  /// **Note**: Packets to addresses without a machine are dropped, but still returned.
+  pub fn tick(&mut self) -> Result<Vec<Packet>, IntcodeError> {
+    let mut sent = Vec::new();
+    let mut received = false;
+    for src in 0..self.nodes.len() {
+      let mut polled = false;
+      loop {
+        let node = &mut self.nodes[src];
+        match node.vm.run_until_io()? {
+          Status::Output(val) => {
+            node.out.push(val);
+            if node.out.len() == 3 {
+              let (dest, x, y) = (node.out[0] as usize, node.out[1], node.out[2]);
+              let packet = Packet { src, dest, x, y };
+              node.out.clear();
+              self.route(packet);
+              sent.push(packet);
+            }
+          },
+          Status::NeedsInput => match node.queue.pop_front() {
+            Some((x, y)) => {
+              node.vm.push_input(x);
+              node.vm.push_input(y);
+              received = true;
+            },
+            None if !polled => {
+              node.vm.push_input(-1);
+              polled = true;
+            },
+            None => break,
+          },
+          _ => break,
+        }
+      }
+    }
+
+    self.idle = sent.is_empty() && !received;
+    if let (true, Some(Some(packet))) = (self.idle, self.nat) {
+      let wake = Packet { src: NAT, dest: 0, ..packet };
+      self.route(wake);
+      sent.push(wake);
+    }
+    Ok(sent)
+  }
+
+  /// Ticks until `pred` matches a sent packet and returns it.
+  ///
+  /// Returns `None` if the network goes idle with nothing for the NAT to send, since nothing will
+  /// ever happen again.
+  pub fn run_until<F: FnMut(&Packet) -> bool>(
+    &mut self,
+    mut pred: F,
+  ) -> Result<Option<Packet>, IntcodeError> {
+    loop {
+      let sent = self.tick()?;
+      if let Some(&packet) = sent.iter().find(|p| pred(p)) {
+        return Ok(Some(packet));
+      }
+      if self.idle && sent.is_empty() {
+        return Ok(None);
+      }
+    }
+  }
+
+  fn route(&mut self, packet: Packet) {
+    if packet.dest == NAT {
+      if let Some(last) = self.nat.as_mut() {
+        *last = Some(packet);
+      }
+    }
+    if let Some(node) = self.nodes.get_mut(packet.dest) {
+      node.queue.push_back((packet.x, packet.y));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::intcode::asm::assemble;
+
+  // Meant for three machines. Machine 0 starts by sending (0, 7) to 1, and every machine forwards
+  // what it receives to the next address with `x` incremented. Machine 2 forwards to the NAT.
+  fn relay() -> Vec<Word> {
+    assemble("
+             in [addr]
+             add [addr], #1, [dest]
+             eq [addr], #2, [tmp]
+             jz [tmp], #boot
+             add #255, #0, [dest]
+      boot:  jnz [addr], #recv
+             out #1
+             out #0
+             out #7
+      recv:  in [x]
+             eq [x], #-1, [tmp]
+             jnz [tmp], #recv
+             in [y]
+             add [x], #1, [x]
+             out [dest]
+             out [x]
+             out [y]
+             jnz #1, #recv
+      addr:  .data 0
+      dest:  .data 0
+      tmp:   .data 0
+      x:     .data 0
+      y:     .data 0
+    ").unwrap()
+  }
+
+  #[test]
+  fn test_first_nat_packet() {
+    let mut net = Network::new(&relay(), 3);
+    let packet = net.run_until(|p| p.dest == NAT).unwrap();
+    assert_eq!(packet, Some(Packet { src: 2, dest: NAT, x: 2, y: 7 }));
+
+    // Without a NAT the network goes quiet for good.
+    assert_eq!(net.run_until(|_| false), Ok(None));
+    assert!(net.is_idle());
+  }
+
+  #[test]
+  fn test_nat_wakes_network() {
+    let mut net = Network::new(&relay(), 3).with_nat();
+    let mut last = None;
+    let repeat = net.run_until(|p| {
+      if p.src != NAT {
+        return false;
+      }
+      let repeated = last == Some(p.y);
+      last = Some(p.y);
+      repeated
+    }).unwrap();
+    assert_eq!(repeat, Some(Packet { src: NAT, dest: 0, x: 5, y: 7 }));
+    assert_eq!(net.nat_packet(), Some(Packet { src: 2, dest: NAT, x: 5, y: 7 }));
+  }
+
+  #[test]
+  fn test_tick_order() {
+    let mut net = Network::new(&relay(), 3);
+    // Everything reaches the NAT in the first tick, since delivery is immediate.
+    let sent: Vec<(usize, usize)> = net.tick().unwrap().iter().map(|p| (p.src, p.dest)).collect();
+    assert_eq!(sent, vec![(0, 1), (1, 2), (2, NAT)]);
+    assert!(!net.is_idle());
+    assert_eq!(net.tick(), Ok(vec![]));
+    assert!(net.is_idle());
+  }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use super::{Instruction, Intcode, Opcode, Tracer};
+
+/// Tracer that counts executions per opcode and per address, and spots loops.
+///
+/// A loop is a taken jump back to an earlier (or the same) address, keyed by its
+/// `(target, jump)` addresses. Its count is the number of times the body was repeated.
+#[derive(Default)]
+pub struct Profiler {
+  steps: u64,
+  by_opcode: HashMap<Opcode, u64>,
+  by_addr: HashMap<usize, (Instruction, u64)>,
+  loops: HashMap<(usize, usize), u64>,
+  last: Option<(usize, Instruction)>,
+  trace: Option<Vec<(usize, Instruction)>>,
+}
+
+impl Profiler {
+  /// Profiler that only keeps counts.
+  pub fn new() -> Self {
+    Profiler::default()
+  }
+
+  /// Profiler that additionally records every executed instruction in order.
+  pub fn recording() -> Self {
+    Profiler { trace: Some(Vec::new()), ..Profiler::default() }
+  }
+
+  pub fn steps(&self) -> u64 {
+    self.steps
+  }
+
+  /// Executed instructions in order, if this profiler is recording.
+  pub fn trace(&self) -> Option<&[(usize, Instruction)]> {
+    self.trace.as_deref()
+  }
+
+  pub fn opcode_count(&self, op: Opcode) -> u64 {
+    self.by_opcode.get(&op).copied().unwrap_or(0)
+  }
+
+  pub fn addr_count(&self, addr: usize) -> u64 {
+    self.by_addr.get(&addr).map_or(0, |&(_, n)| n)
+  }
+
+  /// The `top` most executed addresses with their instruction and count, hottest first.
+  pub fn hottest_addrs(&self, top: usize) -> Vec<(usize, Instruction, u64)> {
+    let mut addrs: Vec<_> = self.by_addr.iter().map(|(&a, &(inst, n))| (a, inst, n)).collect();
+    addrs.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)));
+    addrs.truncate(top);
+    addrs
+  }
+
+  /// The `top` most repeated loops as `(target, jump, repeats)`, hottest first.
+  pub fn hottest_loops(&self, top: usize) -> Vec<(usize, usize, u64)> {
+    let mut loops: Vec<_> = self.loops.iter().map(|(&(t, j), &n)| (t, j, n)).collect();
+    loops.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)));
+    loops.truncate(top);
+    loops
+  }
+
+  /// Summary of where time went, listing the `top` entries of each table.
+  pub fn report(&self, top: usize) -> String {
+    let mut out = String::new();
+    let pct = |n: u64| 100.0 * n as f64 / self.steps.max(1) as f64;
+
+    writeln!(out, "steps: {}", self.steps).unwrap();
+    writeln!(out, "opcodes:").unwrap();
+    let mut ops: Vec<_> = self.by_opcode.iter().map(|(&op, &n)| (op, n)).collect();
+    ops.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.code().cmp(&b.0.code())));
+    for (op, n) in ops {
+      writeln!(out, "  {:<5} {:>10} {:>6.1}%", op.mnemonic(), n, pct(n)).unwrap();
+    }
+    writeln!(out, "hot addresses:").unwrap();
+    for (addr, inst, n) in self.hottest_addrs(top) {
+      let inst = inst.to_string();
+      writeln!(out, "  {:>5}: {:<30} {:>10} {:>6.1}%", addr, inst, n, pct(n)).unwrap();
+    }
+    writeln!(out, "hot loops:").unwrap();
+    for (target, jump, n) in self.hottest_loops(top) {
+      let span = format!("{}..={}", target, jump);
+      writeln!(out, "  {:<12} {:>10} repeats", span, n).unwrap();
+    }
+    out
+  }
+}
+
+impl Tracer for Profiler {
+  fn trace(&mut self, ip: usize, inst: &Instruction, _vm: &Intcode) {
+    if let Some((last_ip, last)) = self.last {
+      let jumped = last_ip + last.size() != ip;
+      if jumped && ip <= last_ip {
+        *self.loops.entry((ip, last_ip)).or_insert(0) += 1;
+      }
+    }
+    self.last = Some((ip, *inst));
+
+    self.steps += 1;
+    *self.by_opcode.entry(inst.op).or_insert(0) += 1;
+    self.by_addr.entry(ip).or_insert((*inst, 0)).1 += 1;
+    if let Some(trace) = self.trace.as_mut() {
+      trace.push((ip, *inst));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::intcode::asm::fixtures::countdown;
+  use crate::intcode::Status;
+
+  #[test]
+  fn test_counts() {
+    let mut vm = countdown();
+    vm.push_input(3);
+    let mut prof = Profiler::new();
+    while vm.run_until_io_traced(&mut prof).unwrap() != Status::Halted {}
+
+    assert_eq!(prof.steps(), 1 + 3 * 3 + 1);
+    assert_eq!(prof.opcode_count(Opcode::Out), 3);
+    assert_eq!(prof.opcode_count(Opcode::Halt), 1);
+    assert_eq!(prof.addr_count(2), 3);
+    assert_eq!(prof.addr_count(0), 1);
+    assert_eq!(prof.hottest_loops(5), vec![(2, 8, 2)]);
+    assert_eq!(prof.trace(), None);
+  }
+
+  #[test]
+  fn test_recording_and_report() {
+    let mut vm = countdown();
+    vm.push_input(1);
+    let mut prof = Profiler::recording();
+    while vm.run_until_io_traced(&mut prof).unwrap() != Status::Halted {}
+
+    let ips: Vec<usize> = prof.trace().unwrap().iter().map(|&(ip, _)| ip).collect();
+    assert_eq!(ips, vec![0, 2, 4, 8, 11]);
+
+    let report = prof.report(1);
+    assert!(report.starts_with("steps: 5\nopcodes:\n"));
+    assert!(report.contains("hot addresses:\n      0: in [12]"));
+    assert!(report.ends_with("hot loops:\n"));
+  }
+}
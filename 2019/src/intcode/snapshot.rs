@@ -0,0 +1,159 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use super::{Intcode, Word};
+
+/// Snapshot format version, bumped if the format changes.
+const VERSION: &str = "1";
+
+// Snapshots are plain text, one `key value` line per field, so they can be diffed and patched by
+// hand. Lists are comma separated:
+//
+//   intcode-snapshot 1
+//   ip 4
+//   rb 0
+//   steps 2
+//   halted false
+//   limit none
+//   inputs 5,6
+//   mem 3,12,4,12,99,...
+impl Intcode {
+  /// Writes the full machine state, including queued input, to `w`.
+  pub fn write_snapshot<W: Write>(&self, mut w: W) -> io::Result<()> {
+    writeln!(w, "intcode-snapshot {}", VERSION)?;
+    writeln!(w, "ip {}", self.ip)?;
+    writeln!(w, "rb {}", self.rb)?;
+    writeln!(w, "steps {}", self.steps)?;
+    writeln!(w, "halted {}", self.halted)?;
+    match self.step_limit {
+      Some(limit) => writeln!(w, "limit {}", limit)?,
+      None => writeln!(w, "limit none")?,
+    }
+    writeln!(w, "inputs {}", join(self.inputs.iter()))?;
+    writeln!(w, "mem {}", join(self.mem.iter()))?;
+    w.flush()
+  }
+
+  /// Restores a machine written by `write_snapshot`.
+  pub fn read_snapshot<R: BufRead>(r: R) -> io::Result<Intcode> {
+    let mut lines = r.lines();
+    let mut next = |key: &str| -> io::Result<String> {
+      let line = lines.next().ok_or_else(|| invalid(format!("missing '{}'", key)))??;
+      match line.split_once(' ') {
+        Some((k, v)) if k == key => Ok(v.to_string()),
+        _ => Err(invalid(format!("expected '{}', got '{}'", key, line))),
+      }
+    };
+
+    if next("intcode-snapshot")? != VERSION {
+      return Err(invalid("unsupported snapshot version".to_string()));
+    }
+    let ip = parse(&next("ip")?)?;
+    let rb = parse(&next("rb")?)?;
+    let steps = parse(&next("steps")?)?;
+    let halted = parse(&next("halted")?)?;
+    let step_limit = match next("limit")?.as_str() {
+      "none" => None,
+      limit => Some(parse(limit)?),
+    };
+    let inputs: VecDeque<Word> = split(&next("inputs")?)?.into_iter().collect();
+    let mem = split(&next("mem")?)?;
+    Ok(Intcode { mem, ip, rb, inputs, halted, steps, step_limit })
+  }
+
+  /// Saves a snapshot to the file at `path`, replacing it if it exists.
+  pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+    self.write_snapshot(BufWriter::new(File::create(path)?))
+  }
+
+  /// Loads a machine from a snapshot file written by `save`.
+  pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Intcode> {
+    Intcode::read_snapshot(BufReader::new(File::open(path)?))
+  }
+}
+
+fn join<'a, I: Iterator<Item = &'a Word>>(words: I) -> String {
+  words.map(|w| w.to_string()).collect::<Vec<_>>().join(",")
+}
+
+fn split(s: &str) -> io::Result<Vec<Word>> {
+  if s.is_empty() {
+    return Ok(Vec::new());
+  }
+  s.split(',').map(parse).collect()
+}
+
+fn parse<T: std::str::FromStr>(s: &str) -> io::Result<T> {
+  s.trim().parse().map_err(|_| invalid(format!("bad value '{}'", s)))
+}
+
+fn invalid(msg: String) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::intcode::asm::fixtures::DOUBLER;
+  use crate::intcode::Status;
+  use std::env;
+  use std::fs;
+
+  #[test]
+  fn test_branch_with_clone() {
+    let mut vm = Intcode::new(&DOUBLER);
+    vm.push_input(4);
+    assert_eq!(vm.run_until_io(), Ok(Status::Output(8)));
+
+    // Explore one branch, then come back to the decision point.
+    let saved = vm.clone();
+    vm.push_input(0);
+    assert_eq!(vm.run_until_io(), Ok(Status::Halted));
+
+    let mut vm = saved;
+    vm.push_input(5);
+    assert_eq!(vm.run_until_io(), Ok(Status::Output(10)));
+  }
+
+  #[test]
+  fn test_round_trip() {
+    let mut vm = Intcode::new(&DOUBLER).with_step_limit(100);
+    vm.push_input(4);
+    vm.push_input(7);
+    assert_eq!(vm.run_until_io(), Ok(Status::Output(8)));
+
+    let mut buf = Vec::new();
+    vm.write_snapshot(&mut buf).unwrap();
+    let text = String::from_utf8(buf.clone()).unwrap();
+    assert!(text.starts_with("intcode-snapshot 1\nip 11\nrb 0\nsteps 4\nhalted false\n"));
+    assert!(text.contains("limit 100\ninputs 7\nmem 3,15,"));
+
+    let mut restored = Intcode::read_snapshot(&buf[..]).unwrap();
+    assert_eq!(restored.ip(), vm.ip());
+    assert_eq!(restored.steps(), vm.steps());
+    assert_eq!(restored.memory(), vm.memory());
+    assert_eq!(restored.run_until_io(), Ok(Status::Output(14)));
+  }
+
+  #[test]
+  fn test_save_load() {
+    let path = env::temp_dir().join(format!("intcode-snapshot-{}.txt", std::process::id()));
+    let mut vm = Intcode::new(&DOUBLER);
+    vm.push_input(3);
+    vm.save(&path).unwrap();
+
+    let mut loaded = Intcode::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.run_until_io(), Ok(Status::Output(6)));
+  }
+
+  #[test]
+  fn test_bad_snapshots() {
+    let err = |s: &str| Intcode::read_snapshot(s.as_bytes()).unwrap_err().to_string();
+    assert_eq!(err(""), "missing 'intcode-snapshot'");
+    assert_eq!(err("intcode-snapshot 2"), "unsupported snapshot version");
+    assert_eq!(err("intcode-snapshot 1\nip x"), "bad value 'x'");
+    assert_eq!(err("intcode-snapshot 1\nrb 0"), "expected 'ip', got 'rb 0'");
+  }
+}
@@ -11,4 +11,6 @@ pub mod day5;
 pub mod day6;
 pub mod day7;
 
+pub mod intcode;
+
 aoc_lib!{ year = 2019 }
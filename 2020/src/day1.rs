@@ -3,7 +3,7 @@ use itertools::Itertools;
 
 #[aoc_generator(day1)]
 pub fn parse(input: &str) -> HashSet<i32> {
-  input.lines().map(|x| x.parse().unwrap()).collect()
+  aoc_common::parse::lines(input).unwrap()
 }
 
 #[aoc(day1, part1)]
@@ -1,10 +1,6 @@
-use lazy_static::lazy_static;
-use regex::Regex;
-use std::collections::HashSet;
-
-lazy_static! {
-  static ref RE_INST: Regex = Regex::new(r"(acc|jmp|nop) ((?:\-|\+)\d+)").unwrap();
-}
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
 
 /// Program is a list of instructions.
 type Program = Vec<Inst>;
@@ -16,42 +12,248 @@ pub enum Inst {
   JMP(i64),
   NOP(i64),
 }
-impl Inst {
-  /// Parses an instruction from a line.
-  fn from(line: &str) -> Inst {
-    let groups = RE_INST.captures(line.trim()).unwrap();
-    let arg: i64 = groups.get(2).unwrap().as_str().parse().unwrap();
-    match groups.get(1).unwrap().as_str() {
-      "acc" => Inst::ACC(arg),
-      "jmp" => Inst::JMP(arg),
-      "nop" => Inst::NOP(arg),
-      _ => panic!("Unknown instruction opcode"),
+impl FromStr for Inst {
+  type Err = ParseError;
+
+  /// Parses a single instruction, reporting errors as if it were line 1.
+  fn from_str(s: &str) -> Result<Inst, ParseError> {
+    match parse_line(s, 1)? {
+      Some(inst) => Ok(inst),
+      None => Err(ParseError { line: 1, column: 1, text: s.to_string(), reason: "no instruction" }),
+    }
+  }
+}
+impl fmt::Display for Inst {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Inst::ACC(arg) => write!(f, "acc {:+}", arg),
+      Inst::JMP(arg) => write!(f, "jmp {:+}", arg),
+      Inst::NOP(arg) => write!(f, "nop {:+}", arg),
     }
   }
 }
 
-/// Runs the given program until it completes or loops, returning a flag for whether it completed 
-/// and the acc value at stop.
-fn run(program: &Program) -> (bool, i64) {
-  let last = program.len() as i64;
-  let mut visited: HashSet<i64> = HashSet::new();
-  let mut acc: i64 = 0;
-  let mut i: i64 = 0;
-  while !visited.contains(&i) {
-    visited.insert(i);
-    i = match &program[i as usize] {
-      Inst::JMP(dff) => i + dff,
+/// Line of a program that isn't a valid instruction.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParseError {
+  /// 1-based line number.
+  pub line: usize,
+  /// 1-based column where `text` starts.
+  pub column: usize,
+  /// The offending text, empty if something is missing.
+  pub text: String,
+  pub reason: &'static str,
+}
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "line {}, column {}: {}", self.line, self.column, self.reason)?;
+    if !self.text.is_empty() {
+      write!(f, " '{}'", self.text)?;
+    }
+    Ok(())
+  }
+}
+impl Error for ParseError {}
+
+/// Parses line number `number`, returning `None` if it's blank or only a comment.
+fn parse_line(line: &str, number: usize) -> Result<Option<Inst>, ParseError> {
+  let code = match line.find('#') {
+    Some(k) => &line[..k],
+    None => line,
+  };
+  let err = |k: usize, text: &str, reason| {
+    ParseError { line: number, column: k + 1, text: text.to_string(), reason }
+  };
+
+  let words = words(code);
+  let (k, opcode) = match words.first() {
+    Some(&word) => word,
+    None => return Ok(None),
+  };
+  let make: fn(i64) -> Inst = match opcode {
+    "acc" => Inst::ACC,
+    "jmp" => Inst::JMP,
+    "nop" => Inst::NOP,
+    _ => return Err(err(k, opcode, "unknown opcode")),
+  };
+  let (ka, arg) = match words.get(1) {
+    Some(&word) => word,
+    None => return Err(err(k + opcode.len(), "", "missing argument")),
+  };
+  if let Some(&(kx, extra)) = words.get(2) {
+    return Err(err(kx, extra, "unexpected text"));
+  }
+  match arg.parse() {
+    Ok(x) if arg.starts_with(&['+', '-'][..]) => Ok(Some(make(x))),
+    _ => Err(err(ka, arg, "expected a signed number like +3")),
+  }
+}
+
+/// Splits `s` on whitespace, keeping the byte offset of each word.
+fn words(s: &str) -> Vec<(usize, &str)> {
+  let mut words = Vec::new();
+  let mut start = None;
+  for (k, c) in s.char_indices().chain(std::iter::once((s.len(), ' '))) {
+    match (c.is_whitespace(), start) {
+      (true, Some(from)) => {
+        words.push((from, &s[from..k]));
+        start = None;
+      },
+      (false, None) => start = Some(k),
+      _ => {},
+    }
+  }
+  words
+}
+
+/// Register state of the console.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Registers {
+  pub acc: i64,
+  pub ip: i64,
+}
+
+/// An instruction the console can execute.
+///
+/// Opcodes are added by wrapping `Inst` in another enum that implements this, and running a
+/// program of that type instead.
+///
+/// **Note**: `Console` finds loops by line alone, so `execute` must move `ip` the same way
+/// whatever acc is. A conditional jump on acc can make a run that would terminate come out as
+/// `Looped` instead.
+pub trait Instruction {
+  /// Updates the registers, including moving `ip` to the next instruction.
+  fn execute(&self, regs: &mut Registers);
+}
+impl Instruction for Inst {
+  fn execute(&self, regs: &mut Registers) {
+    match self {
       Inst::ACC(add) => {
-        acc += add;
-        i + 1
+        regs.acc += add;
+        regs.ip += 1;
+      },
+      Inst::JMP(dff) => regs.ip += dff,
+      Inst::NOP(_) => regs.ip += 1,
+    }
+  }
+}
+
+/// How a program run ended.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RunOutcome {
+  /// Reached the instruction just past the end, with the final acc value.
+  Terminated(i64),
+  /// Was about to run the instruction `at` a second time, with acc as it was at that point.
+  Looped { acc: i64, at: usize },
+  /// Jumped anywhere else outside the program.
+  OutOfBounds { ip: i64 },
+}
+
+/// Handheld game console running a boot code program.
+pub struct Console<'a, I: Instruction = Inst> {
+  program: &'a [I],
+  pub regs: Registers,
+}
+impl<'a, I: Instruction> Console<'a, I> {
+  /// Designated initializer, with all registers at 0.
+  pub fn new(program: &'a [I]) -> Console<'a, I> {
+    Console { program, regs: Registers::default() }
+  }
+
+  /// Executes the instruction at `ip`, returning false without doing anything if `ip` isn't in
+  /// the program.
+  pub fn step(&mut self) -> bool {
+    match self.current() {
+      Some(inst) => {
+        inst.execute(&mut self.regs);
+        true
       },
-      Inst::NOP(_) => i + 1,
+      None => false,
+    }
+  }
+
+  /// Runs until the program terminates, loops or jumps out of bounds.
+  ///
+  /// **Note**: A loop is any line about to run a second time, see `Instruction`.
+  pub fn run(&mut self) -> RunOutcome {
+    self.trace().outcome
+  }
+
+  /// Same as `run`, but keeps every step and the loop, if it found one.
+  pub fn trace(&mut self) -> Trace {
+    let mut steps: Vec<Registers> = Vec::new();
+    // Index into `steps` of each line's first run.
+    let mut first_run: Vec<Option<usize>> = vec![None; self.program.len()];
+    let outcome = loop {
+      let ip = self.regs.ip;
+      if ip == self.program.len() as i64 {
+        break RunOutcome::Terminated(self.regs.acc);
+      }
+      if self.current().is_none() {
+        break RunOutcome::OutOfBounds { ip };
+      }
+      if let Some(k) = first_run[ip as usize] {
+        let cycle = Cycle {
+          entry: ip as usize,
+          body: steps[k..].iter().map(|regs| regs.ip as usize).collect(),
+          acc_delta: self.regs.acc - steps[k].acc,
+        };
+        let outcome = RunOutcome::Looped { acc: self.regs.acc, at: ip as usize };
+        return Trace { steps, outcome, cycle: Some(cycle) };
+      }
+      first_run[ip as usize] = Some(steps.len());
+      steps.push(self.regs);
+      self.step();
     };
-    if i == last {
-      return (true, acc);
+    Trace { steps, outcome, cycle: None }
+  }
+
+  /// Instruction at `ip`, if it's in the program.
+  fn current(&self) -> Option<&'a I> {
+    if self.regs.ip < 0 {
+      return None;
     }
+    self.program.get(self.regs.ip as usize)
+  }
+}
+
+/// Everything that happened in a run.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Trace {
+  /// Registers before each executed instruction, in order.
+  pub steps: Vec<Registers>,
+  pub outcome: RunOutcome,
+  pub cycle: Option<Cycle>,
+}
+
+/// The infinite loop a program got stuck in.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Cycle {
+  /// First line of the loop, which is the line that was about to run a second time.
+  pub entry: usize,
+  /// Lines of one iteration in execution order, starting at `entry`.
+  pub body: Vec<usize>,
+  /// How much acc changes each time around.
+  pub acc_delta: i64,
+}
+
+/// Prints the program one line per instruction, marking the entry of `cycle` with `>` and the
+/// rest of its body with `|`.
+pub fn listing<I: fmt::Display>(program: &[I], cycle: Option<&Cycle>) -> String {
+  let mut out = String::new();
+  for (i, inst) in program.iter().enumerate() {
+    let marker = match cycle {
+      Some(cycle) if cycle.entry == i => '>',
+      Some(cycle) if cycle.body.contains(&i) => '|',
+      _ => ' ',
+    };
+    out.push_str(&format!("{} {:>4}: {}\n", marker, i, inst));
   }
-  (false, acc)
+  if let Some(cycle) = cycle {
+    out.push_str(&format!("loop of {} lines at {}, acc {:+} per iteration\n",
+      cycle.body.len(), cycle.entry, cycle.acc_delta));
+  }
+  out
 }
 
 /// Creates a modified program by swapping jmp <-> nop instructions at the given line `i`.
@@ -67,16 +269,22 @@ fn modified(original: &Program, i: usize) -> Program {
   copied
 }
 
+/// Parses a program, one instruction per line. Blank lines and `#` comments are skipped.
 #[aoc_generator(day8)]
-pub fn parse(input: &str) -> Program {
-  input.trim().lines().map(Inst::from).collect()
+pub fn parse(input: &str) -> Result<Program, ParseError> {
+  let mut program = Program::new();
+  for (k, line) in input.lines().enumerate() {
+    program.extend(parse_line(line, k + 1)?);
+  }
+  Ok(program)
 }
 
 #[aoc(day8, part1)]
 pub fn solve1(program: &Program) -> i64 {
-  let (completed, acc) = run(&program);
-  assert!(!completed, "Part1 program completed, expected loop");
-  acc
+  match Console::new(program).run() {
+    RunOutcome::Looped { acc, .. } => acc,
+    outcome => panic!("Part1 program didn't loop: {:?}", outcome),
+  }
 }
 
 #[aoc(day8, part2)]
@@ -87,17 +295,80 @@ pub fn solve2(program: &Program) -> i64 {
       Inst::JMP(_) | Inst::NOP(_) => true,
       _ => false,
     })
-    // swap jmp <-> nop opcodes at the line and run
-    .map(|i| run(&modified(&program, i)))
-    // find the first modified program that completes
-    .find(|&(completed, _)| completed)
-    // get ending acc value of the modified program
-    .unwrap().1
+    // swap jmp <-> nop opcodes at the line, and find the first modified program that completes
+    .find_map(|i| match Console::new(&modified(&program, i)).run() {
+      RunOutcome::Terminated(acc) => Some(acc),
+      _ => None,
+    })
+    .unwrap()
+}
+
+/// Same as `solve2`, but in linear time.
+///
+/// Works backwards from the end to find every line that already leads to termination, then walks
+/// the original path once for the jmp/nop whose swapped successor is one of those lines.
+///
+/// **Note**: If several swaps would work, this finds the first one on the execution path rather
+/// than the first by line number, which can give a different answer than `solve2`.
+#[aoc(day8, part2, linear)]
+pub fn solve2_linear(program: &Program) -> i64 {
+  let len = program.len() as i64;
+  let in_range = |i: i64| 0 <= i && i <= len;
+
+  // Lines leading into each line, including the end at `len`.
+  let mut preds: Vec<Vec<usize>> = vec![Vec::new(); program.len() + 1];
+  for (i, inst) in program.iter().enumerate() {
+    let next = successor(inst, i as i64);
+    if in_range(next) {
+      preds[next as usize].push(i);
+    }
+  }
+  let mut terminates = vec![false; program.len() + 1];
+  let mut pending = vec![program.len()];
+  while let Some(i) = pending.pop() {
+    if !terminates[i] {
+      terminates[i] = true;
+      pending.extend(&preds[i]);
+    }
+  }
+
+  // The swapped line can't be on the path from its new successor, or the original program
+  // would've terminated too, so the rest of the run just follows the original program.
+  let mut console = Console::new(program);
+  let mut visited = vec![false; program.len()];
+  while let Some(inst) = console.current() {
+    let i = console.regs.ip;
+    let swapped = match inst {
+      Inst::JMP(_) => i + 1,
+      Inst::NOP(arg) => i + arg,
+      Inst::ACC(_) => -1,
+    };
+    if in_range(swapped) && terminates[swapped as usize] {
+      console.regs.ip = swapped;
+      while console.step() {}
+      return console.regs.acc;
+    }
+    if visited[i as usize] {
+      break;
+    }
+    visited[i as usize] = true;
+    console.step();
+  }
+  panic!("No single jmp/nop swap makes the program terminate");
+}
+
+/// Line run after line `i` holding `inst`.
+fn successor(inst: &Inst, i: i64) -> i64 {
+  match inst {
+    Inst::JMP(dff) => i + dff,
+    _ => i + 1,
+  }
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use lazy_static::lazy_static;
 
   lazy_static! {
     static ref SAMPLE: Program = parse(r"
@@ -110,15 +381,132 @@ mod tests {
       acc +1
       jmp -4
       acc +6
-    ");
+    ").unwrap();
   }
 
   #[test]
   fn inst_from() {
-    assert_eq!(Inst::from("acc +42"), Inst::ACC(42));
-    assert_eq!(Inst::from("jmp -13"), Inst::JMP(-13));
-    assert_eq!(Inst::from("nop +0"), Inst::NOP(0));
-    assert_eq!(Inst::from("nop -0"), Inst::NOP(0));
+    assert_eq!("acc +42".parse(), Ok(Inst::ACC(42)));
+    assert_eq!("jmp -13".parse(), Ok(Inst::JMP(-13)));
+    assert_eq!("nop +0".parse(), Ok(Inst::NOP(0)));
+    assert_eq!("nop -0".parse(), Ok(Inst::NOP(0)));
+  }
+
+  #[test]
+  fn parse_comments() {
+    let program = parse("# boot code\n\nacc +1  # bump\n   \njmp -1\n").unwrap();
+    assert_eq!(program, vec![Inst::ACC(1), Inst::JMP(-1)]);
+  }
+
+  #[test]
+  fn parse_errors() {
+    let err = |input: &str| parse(input).unwrap_err().to_string();
+    assert_eq!(err("nop +0\n  mul +2"), "line 2, column 3: unknown opcode 'mul'");
+    assert_eq!(err("acc"), "line 1, column 4: missing argument");
+    assert_eq!(err("\n\njmp 4"), "line 3, column 5: expected a signed number like +3 '4'");
+    assert_eq!(err("jmp +x"), "line 1, column 5: expected a signed number like +3 '+x'");
+    assert_eq!(err("acc +1 +2 # two"), "line 1, column 8: unexpected text '+2'");
+    assert_eq!("".parse::<Inst>().unwrap_err().reason, "no instruction");
+  }
+
+  #[test]
+  fn display_round_trip() {
+    let text: Vec<String> = SAMPLE.iter().map(Inst::to_string).collect();
+    assert_eq!(text[..3], ["nop +0", "acc +1", "jmp +4"]);
+    assert_eq!(parse(&text.join("\n")).unwrap(), *SAMPLE);
+  }
+
+  /// Extends the instruction set with `mul`, which multiplies acc, and `jnz`, which jumps if acc
+  /// isn't 0.
+  enum Extended {
+    Base(Inst),
+    MUL(i64),
+    JNZ(i64),
+  }
+  impl Instruction for Extended {
+    fn execute(&self, regs: &mut Registers) {
+      match self {
+        Extended::Base(inst) => inst.execute(regs),
+        Extended::MUL(x) => {
+          regs.acc *= x;
+          regs.ip += 1;
+        },
+        Extended::JNZ(dff) => regs.ip += if regs.acc != 0 { *dff } else { 1 },
+      }
+    }
+  }
+
+  #[test]
+  fn console_outcomes() {
+    assert_eq!(Console::new(&SAMPLE).run(), RunOutcome::Looped { acc: 5, at: 1 });
+    assert_eq!(Console::new(&modified(&SAMPLE, 7)).run(), RunOutcome::Terminated(8));
+    let jumps_past = parse("nop +0\njmp +5").unwrap();
+    assert_eq!(Console::new(&jumps_past).run(), RunOutcome::OutOfBounds { ip: 6 });
+    assert_eq!(Console::new(&parse("jmp -1").unwrap()).run(), RunOutcome::OutOfBounds { ip: -1 });
+    assert_eq!(Console::new(&Vec::<Inst>::new()).run(), RunOutcome::Terminated(0));
+  }
+
+  #[test]
+  fn console_step() {
+    let mut console = Console::new(&SAMPLE);
+    for _ in 0..3 {
+      assert!(console.step());
+    }
+    assert_eq!(console.regs, Registers { acc: 1, ip: 6 });
+  }
+
+  #[test]
+  fn console_extended() {
+    let program = vec![
+      Extended::Base(Inst::ACC(3)),
+      Extended::MUL(4),
+      Extended::Base(Inst::ACC(-2)),
+    ];
+    assert_eq!(Console::new(&program).run(), RunOutcome::Terminated(10));
+
+    // Counts acc down to 0, which would terminate, but jnz breaks the rule that control flow
+    // doesn't depend on acc, so the second pass is reported as a loop.
+    let program = vec![
+      Extended::Base(Inst::ACC(2)),
+      Extended::Base(Inst::ACC(-1)),
+      Extended::JNZ(-1),
+    ];
+    assert_eq!(Console::new(&program).run(), RunOutcome::Looped { acc: 1, at: 1 });
+    let mut console = Console::new(&program);
+    while console.step() {}
+    assert_eq!(console.regs, Registers { acc: 0, ip: 3 });
+  }
+
+  #[test]
+  fn trace_sample() {
+    let trace = Console::new(&SAMPLE).trace();
+    let lines: Vec<i64> = trace.steps.iter().map(|regs| regs.ip).collect();
+    assert_eq!(lines, vec![0, 1, 2, 6, 7, 3, 4]);
+    assert_eq!(trace.outcome, RunOutcome::Looped { acc: 5, at: 1 });
+    assert_eq!(trace.cycle, Some(Cycle { entry: 1, body: vec![1, 2, 6, 7, 3, 4], acc_delta: 5 }));
+
+    let trace = Console::new(&modified(&SAMPLE, 7)).trace();
+    assert_eq!(trace.outcome, RunOutcome::Terminated(8));
+    assert_eq!(trace.cycle, None);
+    assert_eq!(trace.steps.len(), 6);
+  }
+
+  #[test]
+  fn listing_sample() {
+    let trace = Console::new(&SAMPLE).trace();
+    assert_eq!(listing(&SAMPLE, trace.cycle.as_ref()), [
+      "     0: nop +0",
+      ">    1: acc +1",
+      "|    2: jmp +4",
+      "|    3: acc +3",
+      "|    4: jmp -3",
+      "     5: acc -99",
+      "|    6: acc +1",
+      "|    7: jmp -4",
+      "     8: acc +6",
+      "loop of 6 lines at 1, acc +5 per iteration",
+      "",
+    ].join("\n"));
   }
 
   #[test]
@@ -130,4 +518,18 @@ mod tests {
   fn solve2_sample() {
     assert_eq!(solve2(&SAMPLE), 8);
   }
+
+  #[test]
+  fn solve2_linear_sample() {
+    assert_eq!(solve2_linear(&SAMPLE), solve2(&SAMPLE));
+  }
+
+  #[test]
+  fn solve2_linear_matches() {
+    // A jmp that skips over the loop, and a nop that jumps straight to the end.
+    for program in &["nop +0\njmp +0\nacc +3", "acc +1\nnop +3\njmp -2\nacc +5"] {
+      let program = parse(program).unwrap();
+      assert_eq!(solve2_linear(&program), solve2(&program));
+    }
+  }
 }
\ No newline at end of file
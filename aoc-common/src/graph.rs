@@ -0,0 +1,113 @@
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+
+/// Directed graph stored as adjacency lists.
+#[derive(Clone, Debug)]
+pub struct Graph<N> {
+  edges: HashMap<N, Vec<N>>,
+}
+
+impl<N: Clone + Eq + Hash> Graph<N> {
+  pub fn new() -> Self {
+    Graph { edges: HashMap::new() }
+  }
+
+  /// Adds an edge from `a` to `b`, adding either node if it's new.
+  pub fn add_edge(&mut self, a: N, b: N) {
+    self.edges.entry(b.clone()).or_default();
+    self.edges.entry(a).or_default().push(b);
+  }
+
+  /// Adds edges both ways between `a` and `b`.
+  pub fn add_undirected_edge(&mut self, a: N, b: N) {
+    self.add_edge(a.clone(), b.clone());
+    self.add_edge(b, a);
+  }
+
+  pub fn nodes(&self) -> impl Iterator<Item = &N> {
+    self.edges.keys()
+  }
+
+  pub fn neighbors(&self, node: &N) -> &[N] {
+    self.edges.get(node).map_or(&[], |v| v.as_slice())
+  }
+
+  /// Number of edges on the shortest path from `start` to every node reachable from it.
+  pub fn distances(&self, start: N) -> HashMap<N, usize> {
+    bfs(start, |n| self.neighbors(n).to_vec()).into_iter().map(|(n, (d, _))| (n, d)).collect()
+  }
+
+  /// Nodes on a shortest path from `start` to `goal`, including both.
+  pub fn shortest_path(&self, start: N, goal: &N) -> Option<Vec<N>> {
+    let visited = bfs(start, |n| self.neighbors(n).to_vec());
+    let mut path = vec![goal.clone()];
+    let mut node = goal;
+    while let Some(prev) = &visited.get(node)?.1 {
+      path.push(prev.clone());
+      node = prev;
+    }
+    path.reverse();
+    Some(path)
+  }
+}
+
+impl<N: Clone + Eq + Hash> Default for Graph<N> {
+  fn default() -> Self {
+    Graph::new()
+  }
+}
+
+/// Breadth-first search over a graph given by its `neighbors` function, so it also works on
+/// grids and other implicit graphs.
+///
+/// Returns every reached node with its distance from `start` and the node it was reached from.
+pub fn bfs<N, F, I>(start: N, mut neighbors: F) -> HashMap<N, (usize, Option<N>)>
+where
+  N: Clone + Eq + Hash,
+  F: FnMut(&N) -> I,
+  I: IntoIterator<Item = N>,
+{
+  let mut visited = HashMap::new();
+  let mut queue = VecDeque::new();
+  visited.insert(start.clone(), (0, None));
+  queue.push_back((start, 0));
+  while let Some((node, dist)) = queue.pop_front() {
+    for next in neighbors(&node) {
+      if !visited.contains_key(&next) {
+        visited.insert(next.clone(), (dist + 1, Some(node.clone())));
+        queue.push_back((next, dist + 1));
+      }
+    }
+  }
+  visited
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::grid::Grid;
+  use crate::point::Point;
+
+  #[test]
+  fn test_orbits() {
+    // 2019 day 6 sample, walked both ways.
+    let mut graph = Graph::new();
+    for pair in "COM)B B)C C)D D)E E)F B)G G)H D)I E)J J)K K)L K)YOU I)SAN".split(' ') {
+      let (a, b) = pair.split_at(pair.find(')').unwrap());
+      graph.add_undirected_edge(a, &b[1..]);
+    }
+    assert_eq!(graph.distances("COM")["L"], 7);
+    let path = graph.shortest_path("YOU", &"SAN").unwrap();
+    assert_eq!(path, vec!["YOU", "K", "J", "E", "D", "I", "SAN"]);
+    assert_eq!(graph.shortest_path("YOU", &"nowhere"), None);
+  }
+
+  #[test]
+  fn test_grid_bfs() {
+    let maze = Grid::parse("..#\n#..\n...", |c| c == '.').unwrap();
+    let open = |p: &Point| maze.neighbors4(*p).filter(|&q| maze[q]).collect::<Vec<_>>();
+    let visited = bfs(Point::ORIGIN, open);
+    assert_eq!(visited[&Point::new(2, 2)].0, 4);
+    assert!(!visited.contains_key(&Point::new(2, 0)));
+  }
+}
@@ -0,0 +1,154 @@
+use std::fmt;
+use std::ops::{Index, IndexMut};
+use crate::parse::ParseError;
+use crate::point::Point;
+
+/// Fixed-size rectangular grid, indexed by `Point` with `(0, 0)` at the top left.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Grid<T> {
+  width: usize,
+  height: usize,
+  cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+  /// Grid of `width` by `height` cells all set to `fill`.
+  pub fn new(width: usize, height: usize, fill: T) -> Self where T: Clone {
+    Grid { width, height, cells: vec![fill; width * height] }
+  }
+
+  /// Parses one row per non-blank line, converting each character with `cell`.
+  ///
+  /// Fails on the first line that isn't as wide as the first one.
+  pub fn parse<F: FnMut(char) -> T>(input: &str, mut cell: F) -> Result<Self, ParseError> {
+    let mut width = None;
+    let mut cells = Vec::new();
+    let mut height = 0;
+    for (k, line) in input.lines().enumerate() {
+      let line = line.trim();
+      if line.is_empty() {
+        continue;
+      }
+      let len = line.chars().count();
+      match width {
+        Some(w) if w != len => {
+          let reason = format!("expected {} columns, got {}", w, len);
+          return Err(ParseError { item: k + 1, text: line.to_string(), reason });
+        },
+        _ => width = Some(len),
+      }
+      cells.extend(line.chars().map(&mut cell));
+      height += 1;
+    }
+    Ok(Grid { width: width.unwrap_or(0), height, cells })
+  }
+
+  pub fn width(&self) -> usize {
+    self.width
+  }
+
+  pub fn height(&self) -> usize {
+    self.height
+  }
+
+  pub fn contains(&self, p: Point) -> bool {
+    0 <= p.x && p.x < self.width as i64 && 0 <= p.y && p.y < self.height as i64
+  }
+
+  pub fn get(&self, p: Point) -> Option<&T> {
+    self.offset(p).map(|k| &self.cells[k])
+  }
+
+  pub fn get_mut(&mut self, p: Point) -> Option<&mut T> {
+    self.offset(p).map(move |k| &mut self.cells[k])
+  }
+
+  /// Every point in the grid, row by row.
+  pub fn points(&self) -> impl Iterator<Item = Point> {
+    let (width, height) = (self.width as i64, self.height as i64);
+    (0..height).flat_map(move |y| (0..width).map(move |x| Point::new(x, y)))
+  }
+
+  /// Every point paired with its cell, row by row.
+  pub fn iter(&self) -> impl Iterator<Item = (Point, &T)> {
+    self.points().zip(self.cells.iter())
+  }
+
+  /// Orthogonal neighbors of `p` that are inside the grid.
+  pub fn neighbors4(&self, p: Point) -> impl Iterator<Item = Point> + '_ {
+    p.neighbors4().to_vec().into_iter().filter(move |&q| self.contains(q))
+  }
+
+  /// Neighbors of `p`, including diagonals, that are inside the grid.
+  pub fn neighbors8(&self, p: Point) -> impl Iterator<Item = Point> + '_ {
+    p.neighbors8().to_vec().into_iter().filter(move |&q| self.contains(q))
+  }
+
+  fn offset(&self, p: Point) -> Option<usize> {
+    if self.contains(p) {
+      Some(p.y as usize * self.width + p.x as usize)
+    } else {
+      None
+    }
+  }
+}
+
+/// **Note**: Panics if `p` is outside the grid.
+impl<T> Index<Point> for Grid<T> {
+  type Output = T;
+
+  fn index(&self, p: Point) -> &T {
+    self.get(p).unwrap_or_else(|| panic!("{:?} is outside the grid", p))
+  }
+}
+
+impl<T> IndexMut<Point> for Grid<T> {
+  fn index_mut(&mut self, p: Point) -> &mut T {
+    self.get_mut(p).unwrap_or_else(|| panic!("{:?} is outside the grid", p))
+  }
+}
+
+/// Prints one row per line, with cells printed back to back.
+impl<T: fmt::Display> fmt::Display for Grid<T> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for row in self.cells.chunks(self.width.max(1)) {
+      for cell in row {
+        write!(f, "{}", cell)?;
+      }
+      writeln!(f)?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_and_index() {
+    let mut grid = Grid::parse("..#\n#..\n", |c| c == '#').unwrap();
+    assert_eq!((grid.width(), grid.height()), (3, 2));
+    assert!(grid[Point::new(2, 0)]);
+    assert_eq!(grid.get(Point::new(3, 0)), None);
+    assert_eq!(grid.get(Point::new(0, -1)), None);
+
+    grid[Point::new(0, 0)] = true;
+    let trees: Vec<Point> = grid.iter().filter(|&(_, &t)| t).map(|(p, _)| p).collect();
+    assert_eq!(trees, vec![Point::new(0, 0), Point::new(2, 0), Point::new(0, 1)]);
+  }
+
+  #[test]
+  fn test_parse_ragged() {
+    let err = Grid::parse("...\n..\n", |c| c).unwrap_err();
+    assert_eq!(err.to_string(), "item 2 '..': expected 3 columns, got 2");
+  }
+
+  #[test]
+  fn test_neighbors_and_display() {
+    let grid = Grid::new(3, 2, 0);
+    assert_eq!(grid.neighbors4(Point::ORIGIN).count(), 2);
+    assert_eq!(grid.neighbors8(Point::new(1, 0)).count(), 5);
+    assert_eq!(grid.to_string(), "000\n000\n");
+  }
+}
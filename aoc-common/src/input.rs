@@ -0,0 +1,189 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+use std::str::FromStr;
+use flate2::bufread::MultiGzDecoder;
+use crate::parse;
+
+/// First two bytes of every gzip stream.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Puzzle input read lazily from a file, stdin or any other reader.
+///
+/// Gzip-compressed input is detected from its first bytes and decompressed on the fly.
+pub struct Input {
+  reader: Box<dyn BufRead>,
+}
+
+impl Input {
+  /// Opens the file at `path`, or stdin if `path` is `-`.
+  pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+    let path = path.as_ref();
+    if path == Path::new("-") {
+      Input::from_reader(io::stdin())
+    } else {
+      Input::from_reader(File::open(path)?)
+    }
+  }
+
+  pub fn from_reader<R: Read + 'static>(reader: R) -> io::Result<Self> {
+    let mut reader = BufReader::new(reader);
+    let reader: Box<dyn BufRead> = if reader.fill_buf()?.starts_with(&GZIP_MAGIC) {
+      Box::new(BufReader::new(MultiGzDecoder::new(reader)))
+    } else {
+      Box::new(reader)
+    };
+    Ok(Input { reader })
+  }
+
+  /// Iterates over the lines without their line endings, paired with their 1-based line number.
+  ///
+  /// **Note**: Errors are yielded with the line number in their message. Iteration carries on
+  /// past a line that isn't valid UTF-8, but stops after any other read error.
+  pub fn lines(self) -> Lines {
+    Lines { reader: self.reader, number: 0, failed: false }
+  }
+
+  /// Parses every non-blank line as a `T`, trimming surrounding whitespace.
+  ///
+  /// A line that doesn't parse fails with `InvalidData`, wrapping a `ParseError` whose `item` is
+  /// the line number.
+  pub fn parse_lines<T>(self) -> io::Result<Vec<T>>
+  where
+    T: FromStr,
+    T::Err: fmt::Display,
+  {
+    let mut out = Vec::new();
+    for line in self.lines() {
+      let (number, line) = line?;
+      let text = line.trim();
+      if text.is_empty() {
+        continue;
+      }
+      let value = parse::parse_one(number, text)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+      out.push(value);
+    }
+    Ok(out)
+  }
+
+  /// The whole input as one string.
+  pub fn read_to_string(mut self) -> io::Result<String> {
+    let mut s = String::new();
+    self.reader.read_to_string(&mut s)?;
+    Ok(s)
+  }
+}
+
+/// Iterator returned by `Input::lines`.
+pub struct Lines {
+  reader: Box<dyn BufRead>,
+  number: usize,
+  failed: bool,
+}
+
+impl Iterator for Lines {
+  type Item = io::Result<(usize, String)>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.failed {
+      return None;
+    }
+    let mut bytes = Vec::new();
+    match self.reader.read_until(b'\n', &mut bytes) {
+      Ok(0) => return None,
+      Ok(_) => {},
+      Err(e) => {
+        self.failed = true;
+        let number = self.number + 1;
+        return Some(Err(io::Error::new(e.kind(), format!("line {}: {}", number, e))));
+      },
+    }
+    self.number += 1;
+    if bytes.ends_with(b"\n") {
+      bytes.pop();
+      if bytes.ends_with(b"\r") {
+        bytes.pop();
+      }
+    }
+    let number = self.number;
+    Some(String::from_utf8(bytes).map(|line| (number, line)).map_err(|e| {
+      io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", number, e))
+    }))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parse::ParseError;
+  use std::io::{Cursor, Write};
+  use flate2::write::GzEncoder;
+  use flate2::Compression;
+
+  fn input(bytes: &[u8]) -> Input {
+    Input::from_reader(Cursor::new(bytes.to_vec())).unwrap()
+  }
+
+  #[test]
+  fn test_lines() {
+    let lines: Vec<_> = input(b"a\r\n\nb\xff\nc").lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0].as_ref().unwrap(), &(1, "a".to_string()));
+    assert_eq!(lines[1].as_ref().unwrap(), &(2, String::new()));
+    let err = lines[2].as_ref().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(err.to_string().starts_with("line 3: "));
+    assert_eq!(lines[3].as_ref().unwrap(), &(4, "c".to_string()));
+  }
+
+  #[test]
+  fn test_lines_read_error() {
+    // Reads one line, then fails on every call.
+    struct Broken(bool);
+    impl Read for Broken {
+      fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.0 {
+          return Err(io::Error::new(io::ErrorKind::ConnectionReset, "gone"));
+        }
+        self.0 = true;
+        buf[..2].copy_from_slice(b"a\n");
+        Ok(2)
+      }
+    }
+
+    let lines: Vec<_> = Input::from_reader(Broken(false)).unwrap().lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0].as_ref().unwrap(), &(1, "a".to_string()));
+    let err = lines[1].as_ref().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::ConnectionReset);
+    assert_eq!(err.to_string(), "line 2: gone");
+  }
+
+  #[test]
+  fn test_parse_lines() {
+    assert_eq!(input(b"+1\n\n -2 \n").parse_lines::<i32>().unwrap(), vec![1, -2]);
+
+    let err = input(b"1\n2\n\nx\n").parse_lines::<i32>().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    let err = err.into_inner().unwrap().downcast::<ParseError>().unwrap();
+    assert_eq!(err.item, 4);
+    assert_eq!(err.text, "x");
+  }
+
+  #[test]
+  fn test_gzip() {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(b"3\n4\n").unwrap();
+    let bytes = encoder.finish().unwrap();
+    assert_eq!(input(&bytes).parse_lines::<u8>().unwrap(), vec![3, 4]);
+    assert_eq!(input(b"plain").read_to_string().unwrap(), "plain");
+  }
+
+  #[test]
+  fn test_open_missing() {
+    let err = Input::open("does/not/exist.txt").err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+  }
+}
@@ -0,0 +1,10 @@
+//! Helpers shared by every year: input loading, parsing, points, grids and graphs.
+
+pub mod graph;
+pub mod grid;
+pub mod input;
+pub mod parse;
+pub mod point;
+
+pub use grid::Grid;
+pub use point::Point;
@@ -0,0 +1,127 @@
+use std::error::Error;
+use std::fmt;
+use std::iter::FromIterator;
+use std::str::FromStr;
+
+/// A piece of input that didn't parse.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParseError {
+  /// 1-based position of the failing piece: the line number for `lines`, or the item number for
+  /// `separated` and `ints`.
+  pub item: usize,
+  pub text: String,
+  pub reason: String,
+}
+
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "item {} '{}': {}", self.item, self.text, self.reason)
+  }
+}
+
+impl Error for ParseError {}
+
+/// Parses every non-blank line as a `T`, trimming surrounding whitespace.
+pub fn lines<T, B>(input: &str) -> Result<B, ParseError>
+where
+  T: FromStr,
+  T::Err: fmt::Display,
+  B: FromIterator<T>,
+{
+  input
+    .lines()
+    .enumerate()
+    .map(|(k, line)| (k + 1, line.trim()))
+    .filter(|(_, line)| !line.is_empty())
+    .map(|(k, line)| parse_one(k, line))
+    .collect()
+}
+
+/// Parses a list like `1,2,3` split on `sep`, trimming whitespace around each item.
+pub fn separated<T, B>(input: &str, sep: char) -> Result<B, ParseError>
+where
+  T: FromStr,
+  T::Err: fmt::Display,
+  B: FromIterator<T>,
+{
+  input
+    .trim()
+    .split(sep)
+    .enumerate()
+    .map(|(k, item)| parse_one(k + 1, item.trim()))
+    .collect()
+}
+
+/// Every integer in `s`, ignoring whatever is around them.
+///
+/// A `-` only makes a number negative if it doesn't directly follow a letter or digit, so dates
+/// and ranges like `1518-11-01` or `3-7` come out as positive parts. An integer that doesn't fit
+/// in an `i64` fails with its position among the integers found.
+pub fn ints(s: &str) -> Result<Vec<i64>, ParseError> {
+  let bytes = s.as_bytes();
+  let mut out = Vec::new();
+  let mut k = 0;
+  while k < bytes.len() {
+    if !bytes[k].is_ascii_digit() {
+      k += 1;
+      continue;
+    }
+    let negative = k > 0 && bytes[k - 1] == b'-'
+      && (k < 2 || !bytes[k - 2].is_ascii_alphanumeric());
+    let start = if negative { k - 1 } else { k };
+    while k < bytes.len() && bytes[k].is_ascii_digit() {
+      k += 1;
+    }
+    out.push(parse_one(out.len() + 1, &s[start..k])?);
+  }
+  Ok(out)
+}
+
+pub(crate) fn parse_one<T>(item: usize, text: &str) -> Result<T, ParseError>
+where
+  T: FromStr,
+  T::Err: fmt::Display,
+{
+  text.parse().map_err(|e: T::Err| {
+    ParseError { item, text: text.to_string(), reason: e.to_string() }
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::HashSet;
+
+  #[test]
+  fn test_lines() {
+    let nums: Vec<i32> = lines("1\n -2 \n\n3\n").unwrap();
+    assert_eq!(nums, vec![1, -2, 3]);
+    let set: HashSet<u8> = lines("1\n1\n2").unwrap();
+    assert_eq!(set.len(), 2);
+
+    let err = lines::<u8, Vec<_>>("1\n\nx\n").unwrap_err();
+    assert_eq!(err.item, 3);
+    assert_eq!(err.to_string(), "item 3 'x': invalid digit found in string");
+  }
+
+  #[test]
+  fn test_separated() {
+    let nums: Vec<i64> = separated("1,0, -3,99\n", ',').unwrap();
+    assert_eq!(nums, vec![1, 0, -3, 99]);
+    assert_eq!(separated::<i64, Vec<_>>("1,,2", ',').unwrap_err().item, 2);
+  }
+
+  #[test]
+  fn test_ints() {
+    let nums = ints("[1518-11-01 00:05] Guard #10 begins shift").unwrap();
+    assert_eq!(nums, vec![1518, 11, 1, 0, 5, 10]);
+    assert_eq!(ints("x=-3, y=4..-12").unwrap(), vec![-3, 4, -12]);
+    assert_eq!(ints("no numbers").unwrap(), Vec::<i64>::new());
+
+    let err = ints("x=1, y=99999999999999999999").unwrap_err();
+    assert_eq!(err.item, 2);
+    assert_eq!(err.text, "99999999999999999999");
+    assert_eq!(err.reason, "number too large to fit in target type");
+    assert_eq!(ints("-9223372036854775808").unwrap(), vec![i64::MIN]);
+  }
+}
@@ -0,0 +1,108 @@
+use std::ops::{Add, AddAssign, Mul, Sub};
+
+/// Integer point on a 2D plane, with `y` growing downwards like rows of text input.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct Point {
+  pub x: i64,
+  pub y: i64,
+}
+
+impl Point {
+  pub const ORIGIN: Point = Point { x: 0, y: 0 };
+  pub const UP: Point = Point { x: 0, y: -1 };
+  pub const DOWN: Point = Point { x: 0, y: 1 };
+  pub const LEFT: Point = Point { x: -1, y: 0 };
+  pub const RIGHT: Point = Point { x: 1, y: 0 };
+
+  pub fn new(x: i64, y: i64) -> Self {
+    Point { x, y }
+  }
+
+  pub fn manhattan(self, other: Point) -> i64 {
+    (self.x - other.x).abs() + (self.y - other.y).abs()
+  }
+
+  /// The 4 orthogonally adjacent points, clockwise from up.
+  pub fn neighbors4(self) -> [Point; 4] {
+    [self + Point::UP, self + Point::RIGHT, self + Point::DOWN, self + Point::LEFT]
+  }
+
+  /// The 8 surrounding points, clockwise from up.
+  pub fn neighbors8(self) -> [Point; 8] {
+    let (x, y) = (self.x, self.y);
+    [
+      Point::new(x, y - 1), Point::new(x + 1, y - 1), Point::new(x + 1, y),
+      Point::new(x + 1, y + 1), Point::new(x, y + 1), Point::new(x - 1, y + 1),
+      Point::new(x - 1, y), Point::new(x - 1, y - 1),
+    ]
+  }
+
+  /// Rotates a direction 90 degrees clockwise, as seen on screen.
+  pub fn turn_right(self) -> Point {
+    Point { x: -self.y, y: self.x }
+  }
+
+  /// Rotates a direction 90 degrees counter-clockwise, as seen on screen.
+  pub fn turn_left(self) -> Point {
+    Point { x: self.y, y: -self.x }
+  }
+}
+
+impl Add for Point {
+  type Output = Point;
+
+  fn add(self, other: Point) -> Point {
+    Point { x: self.x + other.x, y: self.y + other.y }
+  }
+}
+
+impl AddAssign for Point {
+  fn add_assign(&mut self, other: Point) {
+    *self = *self + other;
+  }
+}
+
+impl Sub for Point {
+  type Output = Point;
+
+  fn sub(self, other: Point) -> Point {
+    Point { x: self.x - other.x, y: self.y - other.y }
+  }
+}
+
+impl Mul<i64> for Point {
+  type Output = Point;
+
+  fn mul(self, k: i64) -> Point {
+    Point { x: self.x * k, y: self.y * k }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_arithmetic() {
+    let p = Point::new(3, -4);
+    assert_eq!(p + Point::RIGHT * 2, Point::new(5, -4));
+    assert_eq!(p - p, Point::ORIGIN);
+    assert_eq!(p.manhattan(Point::ORIGIN), 7);
+  }
+
+  #[test]
+  fn test_turns() {
+    assert_eq!(Point::UP.turn_right(), Point::RIGHT);
+    assert_eq!(Point::RIGHT.turn_right(), Point::DOWN);
+    assert_eq!(Point::UP.turn_left(), Point::LEFT);
+    assert_eq!(Point::UP.turn_left().turn_right(), Point::UP);
+  }
+
+  #[test]
+  fn test_neighbors() {
+    let p = Point::new(1, 1);
+    assert!(p.neighbors4().iter().all(|&q| q.manhattan(p) == 1));
+    assert_eq!(p.neighbors8().len(), 8);
+    assert!(!p.neighbors8().contains(&p));
+  }
+}